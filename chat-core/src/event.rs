@@ -1,6 +1,10 @@
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::chat_type::ChatType;
+use crate::{
+    chat_type::ChatType,
+    message_kind::{MessageKind, MessagePayload},
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ChatEvent {
@@ -9,6 +13,11 @@ pub enum ChatEvent {
     UserJoin(UserJoinEvent),
     UserLeave(UserLeaveEvent),
     MessageSend(MessageSendEvent),
+    MessageEdit(MessageEditEvent),
+    MessageDelete(MessageDeleteEvent),
+    ReactionChanged(ReactionChangedEvent),
+    ReadReceipt(ReadReceiptEvent),
+    MessagePinned(MessagePinnedEvent),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,7 +56,129 @@ pub struct MessageSendEvent {
     pub sender_id: i64,
     pub content: String,
     pub members: Vec<i64>,
-    pub attachments: Option<Vec<String>>,
+    // 旧版本的事件中附件可能为空或者只有地址
+    #[serde(default, deserialize_with = "deserialize_attachments")]
+    pub attachments: Vec<Attachment>,
+    // 话题回复时带上父消息的回复信息，客户端据此更新话题角标
+    #[serde(default)]
+    pub thread: Option<ThreadInfo>,
+    // 消息中 @ 到的用户，@all 时是除发送者以外的所有成员
+    #[serde(default)]
+    pub mentioned_user_ids: Vec<i64>,
+    #[serde(default)]
+    pub mention_all: bool,
+    // 客户端生成的消息ID，发送者的其他设备据此替换本地的待发送消息
+    #[serde(default)]
+    pub client_msg_id: Option<String>,
+    // 消息类型和结构化内容，旧的消息都是文本
+    #[serde(default)]
+    pub kind: MessageKind,
+    #[serde(default)]
+    pub payload: Option<MessagePayload>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadInfo {
+    pub parent_message_id: i64,
+    pub reply_count: i32,
+    // 最新一条回复的发送时间，回复全部删除后为空
+    #[serde(default)]
+    pub last_reply_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageEditEvent {
+    pub message_id: i64,
+    pub chat_id: i64,
+    pub editor_id: i64,
+    pub content: String,
+    pub members: Vec<i64>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageDeleteEvent {
+    pub message_id: i64,
+    pub chat_id: i64,
+    pub deleted_by: i64,
+    pub members: Vec<i64>,
+    // 删除话题回复时带上父消息更新后的回复信息
+    #[serde(default)]
+    pub thread: Option<ThreadInfo>,
+}
+// 表情回应变化，count 是变化后该表情的回应总数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReactionChangedEvent {
+    pub message_id: i64,
+    pub chat_id: i64,
+    pub user_id: i64,
+    pub emoji: String,
+    pub added: bool,
+    pub count: i64,
+    pub members: Vec<i64>,
+}
+// 成员的已读位置前进，客户端据此展示“已读”
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadReceiptEvent {
+    pub chat_id: i64,
+    pub user_id: i64,
+    pub last_read_message_id: i64,
+    pub members: Vec<i64>,
+}
+// 消息置顶或取消置顶，pinned 为 false 表示取消置顶
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessagePinnedEvent {
+    pub message_id: i64,
+    pub chat_id: i64,
+    pub operator_id: i64,
+    pub pinned: bool,
+    pub members: Vec<i64>,
+}
+
+// 消息附件，客户端收到消息后可以直接展示
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Attachment {
+    pub url: String,
+    pub filename: String,
+    pub size: i64,
+    pub mime_type: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    // 图片的缩略图，后台生成，刚上传的图片可能还没有
+    #[serde(default)]
+    pub thumbnails: Vec<Thumbnail>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Thumbnail {
+    pub url: String,
+    pub width: i32,
+    pub height: i32,
+}
+
+// 兼容旧的附件格式：null 或者地址列表
+fn deserialize_attachments<'de, D>(deserializer: D) -> Result<Vec<Attachment>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum AttachmentOrUrl {
+        Attachment(Attachment),
+        Url(String),
+    }
+
+    let list: Option<Vec<AttachmentOrUrl>> = Option::deserialize(deserializer)?;
+    Ok(list
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| match item {
+            AttachmentOrUrl::Attachment(attachment) => attachment,
+            AttachmentOrUrl::Url(url) => Attachment {
+                filename: url.rsplit('/').next().unwrap_or_default().to_string(),
+                url,
+                ..Default::default()
+            },
+        })
+        .collect())
 }
 
 impl ChatCreateEvent {
@@ -111,7 +242,8 @@ impl MessageSendEvent {
         sender_id: i64,
         content: impl Into<String>,
         members: Vec<i64>,
-        attachments: Option<Vec<String>>,
+        attachments: Vec<Attachment>,
+        thread: Option<ThreadInfo>,
     ) -> Self {
         Self {
             message_id,
@@ -120,6 +252,151 @@ impl MessageSendEvent {
             content: content.into(),
             members,
             attachments,
+            thread,
+            mentioned_user_ids: vec![],
+            mention_all: false,
+            client_msg_id: None,
+            kind: MessageKind::Text,
+            payload: None,
+        }
+    }
+
+    // 设置消息提及的用户
+    pub fn with_mentions(mut self, mentioned_user_ids: Vec<i64>, mention_all: bool) -> Self {
+        self.mentioned_user_ids = mentioned_user_ids;
+        self.mention_all = mention_all;
+        self
+    }
+
+    // 设置客户端生成的消息ID
+    pub fn with_client_msg_id(mut self, client_msg_id: Option<String>) -> Self {
+        self.client_msg_id = client_msg_id;
+        self
+    }
+
+    // 设置消息类型和结构化内容
+    pub fn with_kind(mut self, kind: MessageKind, payload: Option<MessagePayload>) -> Self {
+        self.kind = kind;
+        self.payload = payload;
+        self
+    }
+}
+
+impl ThreadInfo {
+    pub fn new(
+        parent_message_id: i64,
+        reply_count: i32,
+        last_reply_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            parent_message_id,
+            reply_count,
+            last_reply_at,
+        }
+    }
+}
+
+impl MessageEditEvent {
+    pub fn new(
+        message_id: i64,
+        chat_id: i64,
+        editor_id: i64,
+        content: impl Into<String>,
+        members: Vec<i64>,
+    ) -> Self {
+        Self {
+            message_id,
+            chat_id,
+            editor_id,
+            content: content.into(),
+            members,
+        }
+    }
+}
+
+impl MessageDeleteEvent {
+    pub fn new(message_id: i64, chat_id: i64, deleted_by: i64, members: Vec<i64>) -> Self {
+        Self {
+            message_id,
+            chat_id,
+            deleted_by,
+            members,
+            thread: None,
+        }
+    }
+
+    // 设置话题回复的父消息信息
+    pub fn with_thread(mut self, thread: Option<ThreadInfo>) -> Self {
+        self.thread = thread;
+        self
+    }
+}
+
+impl ReactionChangedEvent {
+    pub fn new(
+        message_id: i64,
+        chat_id: i64,
+        user_id: i64,
+        emoji: impl Into<String>,
+        added: bool,
+        count: i64,
+        members: Vec<i64>,
+    ) -> Self {
+        Self {
+            message_id,
+            chat_id,
+            user_id,
+            emoji: emoji.into(),
+            added,
+            count,
+            members,
+        }
+    }
+}
+
+impl ReadReceiptEvent {
+    pub fn new(chat_id: i64, user_id: i64, last_read_message_id: i64, members: Vec<i64>) -> Self {
+        Self {
+            chat_id,
+            user_id,
+            last_read_message_id,
+            members,
+        }
+    }
+}
+
+impl MessagePinnedEvent {
+    pub fn new(
+        message_id: i64,
+        chat_id: i64,
+        operator_id: i64,
+        pinned: bool,
+        members: Vec<i64>,
+    ) -> Self {
+        Self {
+            message_id,
+            chat_id,
+            operator_id,
+            pinned,
+            members,
+        }
+    }
+}
+
+impl ChatEvent {
+    // 需要接收这个事件的聊天室成员
+    pub fn members(&self) -> &[i64] {
+        match self {
+            Self::ChatCreate(event) => &event.members,
+            Self::ChatDrop(event) => &event.members,
+            Self::UserJoin(event) => &event.members,
+            Self::UserLeave(event) => &event.members,
+            Self::MessageSend(event) => &event.members,
+            Self::MessageEdit(event) => &event.members,
+            Self::MessageDelete(event) => &event.members,
+            Self::ReactionChanged(event) => &event.members,
+            Self::ReadReceipt(event) => &event.members,
+            Self::MessagePinned(event) => &event.members,
         }
     }
 }
@@ -149,3 +426,79 @@ impl From<MessageSendEvent> for ChatEvent {
         Self::MessageSend(value)
     }
 }
+impl From<MessageEditEvent> for ChatEvent {
+    fn from(value: MessageEditEvent) -> Self {
+        Self::MessageEdit(value)
+    }
+}
+impl From<MessageDeleteEvent> for ChatEvent {
+    fn from(value: MessageDeleteEvent) -> Self {
+        Self::MessageDelete(value)
+    }
+}
+impl From<ReactionChangedEvent> for ChatEvent {
+    fn from(value: ReactionChangedEvent) -> Self {
+        Self::ReactionChanged(value)
+    }
+}
+impl From<ReadReceiptEvent> for ChatEvent {
+    fn from(value: ReadReceiptEvent) -> Self {
+        Self::ReadReceipt(value)
+    }
+}
+impl From<MessagePinnedEvent> for ChatEvent {
+    fn from(value: MessagePinnedEvent) -> Self {
+        Self::MessagePinned(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_members() {
+        let event: ChatEvent = ReadReceiptEvent::new(1, 2, 3, vec![1, 2]).into();
+        assert_eq!(event.members(), &[1, 2]);
+        let event: ChatEvent = MessageDeleteEvent::new(3, 1, 2, vec![2, 4]).into();
+        assert_eq!(event.members(), &[2, 4]);
+    }
+
+    #[test]
+    fn test_legacy_message_send_event() {
+        let json = r#"{"MessageSend":{"message_id":1,"chat_id":1,"sender_id":1,"content":"hi","members":[1,2],"attachments":null}}"#;
+        let ChatEvent::MessageSend(event) = serde_json::from_str(json).unwrap() else {
+            panic!("expected MessageSend");
+        };
+        assert!(event.attachments.is_empty());
+
+        let json = r#"{"MessageSend":{"message_id":1,"chat_id":1,"sender_id":1,"content":"","members":[1],"attachments":["/api/files/1/ab/abc.png"]}}"#;
+        let ChatEvent::MessageSend(event) = serde_json::from_str(json).unwrap() else {
+            panic!("expected MessageSend");
+        };
+        assert_eq!(event.attachments[0].url, "/api/files/1/ab/abc.png");
+        assert_eq!(event.attachments[0].filename, "abc.png");
+
+        let event = MessageSendEvent::new(
+            1,
+            1,
+            1,
+            "",
+            vec![1],
+            vec![Attachment {
+                url: "/api/files/1/ab/abc.png".to_string(),
+                filename: "a.png".to_string(),
+                size: 3,
+                mime_type: "image/png".to_string(),
+                ..Default::default()
+            }],
+            None,
+        );
+        let json = serde_json::to_string(&ChatEvent::from(event)).unwrap();
+        let ChatEvent::MessageSend(event) = serde_json::from_str(&json).unwrap() else {
+            panic!("expected MessageSend");
+        };
+        assert_eq!(event.attachments[0].filename, "a.png");
+        assert_eq!(event.attachments[0].size, 3);
+    }
+}
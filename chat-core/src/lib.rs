@@ -1,11 +1,16 @@
-use crate::models::user::CurUser;
+use std::future::Future;
+
+use crate::utils::jwt::VerifiedToken;
 
 pub mod chat_type;
 pub mod event;
+pub mod message_kind;
 pub mod middlewares;
 pub mod models;
 pub mod utils;
 
 pub trait TokenVerify {
-    fn verify_token(&self, token: &str) -> Result<CurUser, jwt_simple::Error>;
+    fn verify_token(&self, token: &str) -> Result<VerifiedToken, jwt_simple::Error>;
+
+    fn is_token_revoked(&self, jti: &str) -> impl Future<Output = anyhow::Result<bool>> + Send;
 }
@@ -0,0 +1,408 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Decode, Encode, MySql, encode::IsNull, mysql::MySqlTypeInfo, prelude::Type};
+
+// 文本消息的最大字符数
+pub const MAX_CONTENT_LEN: usize = 10000;
+// 卡片的限制
+const MAX_CARD_TITLE_LEN: usize = 200;
+const MAX_CARD_FIELDS: usize = 25;
+const MAX_CARD_FIELD_NAME_LEN: usize = 256;
+const MAX_CARD_FIELD_VALUE_LEN: usize = 1024;
+const MAX_CARD_ACTIONS: usize = 5;
+const MAX_CARD_ACTION_LABEL_LEN: usize = 50;
+const MAX_URL_LEN: usize = 2048;
+
+// 消息类型，客户端按类型渲染
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+    // 纯文本
+    #[default]
+    Text,
+    // Markdown 文本
+    Markdown,
+    // 图片，content 是图片说明
+    Image,
+    // 文件，content 是文件说明
+    File,
+    // 系统消息，只能由服务端生成
+    System,
+    // 结构化卡片，内容在 payload 中
+    Card,
+}
+
+// 消息的结构化内容
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessagePayload {
+    Card(Card),
+    System(SystemNotice),
+}
+
+// 卡片消息，机器人可以用来发送通知
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Card {
+    pub title: String,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub fields: Vec<CardField>,
+    #[serde(default)]
+    pub actions: Vec<CardAction>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CardField {
+    pub name: String,
+    pub value: String,
+    // 客户端可以把相邻的 inline 字段放在同一行
+    #[serde(default)]
+    pub inline: bool,
+}
+
+// 卡片上的按钮，点击后打开链接
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CardAction {
+    pub label: String,
+    pub url: String,
+}
+
+// 系统消息记录的聊天室变化，客户端可以据此本地化展示
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SystemNotice {
+    pub action: SystemAction,
+    // 操作人
+    pub operator_id: i64,
+    // 加入、离开或被移出的成员
+    #[serde(default)]
+    pub user_ids: Vec<i64>,
+    // 修改后的聊天标题
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemAction {
+    // 加入聊天，操作人不是成员本人时表示被邀请
+    Join,
+    // 主动离开
+    Leave,
+    // 被管理员移出
+    Kick,
+    // 修改聊天标题
+    Rename,
+}
+
+impl MessageKind {
+    // 按消息类型校验内容，attachment_mime_types 是消息附件的类型
+    pub fn validate(
+        &self,
+        content: &str,
+        payload: Option<&MessagePayload>,
+        attachment_mime_types: &[&str],
+    ) -> Result<(), String> {
+        check_len("content", content, MAX_CONTENT_LEN)?;
+        match (self, payload) {
+            (MessageKind::Card, Some(MessagePayload::Card(card))) => card.validate(),
+            (MessageKind::Card, None) => Err("card message requires a card payload".to_string()),
+            (MessageKind::System, Some(MessagePayload::System(_))) => {
+                if content.trim().is_empty() {
+                    return Err("content can not be empty".to_string());
+                }
+                Ok(())
+            }
+            (_, Some(_)) => Err(format!("{} message can not have a payload", self.as_str())),
+            (MessageKind::Text, None) => {
+                // 只有附件的消息可以没有文字
+                if content.trim().is_empty() && attachment_mime_types.is_empty() {
+                    return Err("content can not be empty".to_string());
+                }
+                Ok(())
+            }
+            (MessageKind::Markdown | MessageKind::System, None) => {
+                if content.trim().is_empty() {
+                    return Err("content can not be empty".to_string());
+                }
+                Ok(())
+            }
+            (MessageKind::Image, None) => {
+                if attachment_mime_types.is_empty()
+                    || !attachment_mime_types
+                        .iter()
+                        .all(|m| m.starts_with("image/"))
+                {
+                    return Err("image message requires image attachments".to_string());
+                }
+                Ok(())
+            }
+            (MessageKind::File, None) => {
+                if attachment_mime_types.is_empty() {
+                    return Err("file message requires attachments".to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.into()
+    }
+}
+
+impl Card {
+    pub fn validate(&self) -> Result<(), String> {
+        check_required("card title", &self.title, MAX_CARD_TITLE_LEN)?;
+        if let Some(text) = &self.text {
+            check_len("card text", text, MAX_CONTENT_LEN)?;
+        }
+        if self.fields.len() > MAX_CARD_FIELDS {
+            return Err(format!("card can have at most {} fields", MAX_CARD_FIELDS));
+        }
+        for field in self.fields.iter() {
+            check_required("card field name", &field.name, MAX_CARD_FIELD_NAME_LEN)?;
+            check_required("card field value", &field.value, MAX_CARD_FIELD_VALUE_LEN)?;
+        }
+        if self.actions.len() > MAX_CARD_ACTIONS {
+            return Err(format!(
+                "card can have at most {} actions",
+                MAX_CARD_ACTIONS
+            ));
+        }
+        for action in self.actions.iter() {
+            check_required(
+                "card action label",
+                &action.label,
+                MAX_CARD_ACTION_LABEL_LEN,
+            )?;
+            check_len("card action url", &action.url, MAX_URL_LEN)?;
+            if !(action.url.starts_with("https://") || action.url.starts_with("http://")) {
+                return Err(format!("invalid card action url: {}", action.url));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_len(name: &str, value: &str, max: usize) -> Result<(), String> {
+    if value.chars().count() > max {
+        return Err(format!("{} is longer than {} characters", name, max));
+    }
+    Ok(())
+}
+
+fn check_required(name: &str, value: &str, max: usize) -> Result<(), String> {
+    if value.trim().is_empty() {
+        return Err(format!("{} can not be empty", name));
+    }
+    check_len(name, value, max)
+}
+
+// 实现 Type：消息类型在 MySQL 中保存为字符串
+impl Type<MySql> for MessageKind {
+    fn type_info() -> MySqlTypeInfo {
+        <&str as Type<MySql>>::type_info()
+    }
+
+    fn compatible(ty: &MySqlTypeInfo) -> bool {
+        <&str as Type<MySql>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, MySql> for MessageKind {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <MySql as sqlx::Database>::ArgumentBuffer<'_>,
+    ) -> std::result::Result<IsNull, sqlx::error::BoxDynError> {
+        let s: &str = self.into();
+        <&str as Encode<MySql>>::encode(s, buf)
+    }
+}
+
+impl<'r> Decode<'r, MySql> for MessageKind {
+    fn decode(
+        value: <MySql as sqlx::Database>::ValueRef<'r>,
+    ) -> std::result::Result<Self, sqlx::error::BoxDynError> {
+        let s: &str = <&str as Decode<MySql>>::decode(value)?;
+        match s {
+            "text" => Ok(MessageKind::Text),
+            "markdown" => Ok(MessageKind::Markdown),
+            "image" => Ok(MessageKind::Image),
+            "file" => Ok(MessageKind::File),
+            "system" => Ok(MessageKind::System),
+            "card" => Ok(MessageKind::Card),
+            _ => Err(format!("unknown MessageKind variant: '{}'", s).into()),
+        }
+    }
+}
+
+impl From<&MessageKind> for &'static str {
+    fn from(k: &MessageKind) -> Self {
+        match k {
+            MessageKind::Text => "text",
+            MessageKind::Markdown => "markdown",
+            MessageKind::Image => "image",
+            MessageKind::File => "file",
+            MessageKind::System => "system",
+            MessageKind::Card => "card",
+        }
+    }
+}
+
+// 结构化内容在 MySQL 中保存为 JSON 字符串
+impl Type<MySql> for MessagePayload {
+    fn type_info() -> MySqlTypeInfo {
+        <&str as Type<MySql>>::type_info()
+    }
+
+    fn compatible(ty: &MySqlTypeInfo) -> bool {
+        <&str as Type<MySql>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, MySql> for MessagePayload {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <MySql as sqlx::Database>::ArgumentBuffer<'_>,
+    ) -> std::result::Result<IsNull, sqlx::error::BoxDynError> {
+        let s = serde_json::to_string(self)?;
+        <String as Encode<MySql>>::encode(s, buf)
+    }
+}
+
+impl<'r> Decode<'r, MySql> for MessagePayload {
+    fn decode(
+        value: <MySql as sqlx::Database>::ValueRef<'r>,
+    ) -> std::result::Result<Self, sqlx::error::BoxDynError> {
+        let s: &str = <&str as Decode<MySql>>::decode(value)?;
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card() -> Card {
+        Card {
+            title: "构建完成".to_string(),
+            text: Some("main 分支构建成功".to_string()),
+            fields: vec![CardField {
+                name: "耗时".to_string(),
+                value: "3m".to_string(),
+                inline: true,
+            }],
+            actions: vec![CardAction {
+                label: "查看".to_string(),
+                url: "https://ci.example.com/1".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_validate_message_kind() {
+        assert!(MessageKind::Text.validate("hi", None, &[]).is_ok());
+        assert!(MessageKind::Text.validate("", None, &["image/png"]).is_ok());
+        assert!(MessageKind::Text.validate("  ", None, &[]).is_err());
+        assert!(MessageKind::Markdown.validate("**hi**", None, &[]).is_ok());
+        assert!(
+            MessageKind::Markdown
+                .validate("", None, &["image/png"])
+                .is_err()
+        );
+        assert!(
+            MessageKind::Image
+                .validate("", None, &["image/png"])
+                .is_ok()
+        );
+        assert!(
+            MessageKind::Image
+                .validate("", None, &["application/pdf"])
+                .is_err()
+        );
+        assert!(MessageKind::Image.validate("", None, &[]).is_err());
+        assert!(
+            MessageKind::File
+                .validate("", None, &["application/pdf"])
+                .is_ok()
+        );
+        assert!(MessageKind::File.validate("", None, &[]).is_err());
+        assert!(
+            MessageKind::Text
+                .validate(&"a".repeat(MAX_CONTENT_LEN + 1), None, &[])
+                .is_err()
+        );
+
+        let payload = MessagePayload::Card(card());
+        assert!(MessageKind::Card.validate("", Some(&payload), &[]).is_ok());
+        assert!(MessageKind::Card.validate("", None, &[]).is_err());
+        assert!(
+            MessageKind::Text
+                .validate("hi", Some(&payload), &[])
+                .is_err()
+        );
+
+        let notice = MessagePayload::System(SystemNotice {
+            action: SystemAction::Join,
+            operator_id: 1,
+            user_ids: vec![2],
+            title: None,
+        });
+        assert!(
+            MessageKind::System
+                .validate("张三 加入了聊天", Some(&notice), &[])
+                .is_ok()
+        );
+        assert!(
+            MessageKind::System
+                .validate("", Some(&notice), &[])
+                .is_err()
+        );
+        assert!(
+            MessageKind::Text
+                .validate("hi", Some(&notice), &[])
+                .is_err()
+        );
+        assert!(
+            MessageKind::System
+                .validate("hi", Some(&payload), &[])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_card() {
+        assert!(card().validate().is_ok());
+        let mut c = card();
+        c.title = " ".to_string();
+        assert!(c.validate().is_err());
+        let mut c = card();
+        c.actions[0].url = "javascript:alert(1)".to_string();
+        assert!(c.validate().is_err());
+        let mut c = card();
+        c.fields = vec![c.fields[0].clone(); MAX_CARD_FIELDS + 1];
+        assert!(c.validate().is_err());
+    }
+
+    #[test]
+    fn test_payload_json() {
+        let payload = MessagePayload::Card(card());
+        let json = serde_json::to_string(&payload).unwrap();
+        assert!(json.starts_with(r#"{"type":"card","title":"构建完成""#));
+        assert_eq!(
+            serde_json::from_str::<MessagePayload>(&json).unwrap(),
+            payload
+        );
+
+        let json = r#"{"type":"system","action":"rename","operator_id":1,"title":"新标题"}"#;
+        assert_eq!(
+            serde_json::from_str::<MessagePayload>(json).unwrap(),
+            MessagePayload::System(SystemNotice {
+                action: SystemAction::Rename,
+                operator_id: 1,
+                user_ids: vec![],
+                title: Some("新标题".to_string()),
+            })
+        );
+    }
+}
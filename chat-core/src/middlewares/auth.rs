@@ -48,12 +48,8 @@ where
             }
         };
 
-    let req = match state.verify_token(&token) {
-        Ok(u) => {
-            let mut request = Request::from_parts(parts, body);
-            request.extensions_mut().insert(u);
-            request
-        }
+    let token = match state.verify_token(&token) {
+        Ok(t) => t,
         Err(e) => {
             let msg = format!("verify token failed: {}", e);
             warn!(msg);
@@ -61,5 +57,111 @@ where
         }
     };
 
+    // 已登出的token不允许再访问，redis不可用时同样拒绝
+    match state.is_token_revoked(&token.jti).await {
+        Ok(false) => {}
+        Ok(true) => {
+            let msg = "token has been revoked".to_string();
+            warn!(msg);
+            return (StatusCode::UNAUTHORIZED, msg).into_response();
+        }
+        Err(e) => {
+            let msg = format!("check token revocation failed: {}", e);
+            warn!(msg);
+            return (StatusCode::UNAUTHORIZED, msg).into_response();
+        }
+    }
+
+    let mut req = Request::from_parts(parts, body);
+    req.extensions_mut().insert(token.user.clone());
+    req.extensions_mut().insert(token);
+
     next.run(req).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::user::CurUser,
+        utils::{
+            jwt::{DecodingKey, EncodingKey, VerifiedToken},
+            revoked_token::RevokedTokens,
+        },
+    };
+    use axum::{Router, body::Body, middleware::from_fn_with_state, routing::get};
+    use tower::ServiceExt;
+
+    #[derive(Debug, Clone)]
+    struct TestState {
+        decoding_key: DecodingKey,
+        revoked_tokens: RevokedTokens,
+    }
+
+    impl TokenVerify for TestState {
+        fn verify_token(&self, token: &str) -> Result<VerifiedToken, jwt_simple::Error> {
+            self.decoding_key.decode(token)
+        }
+
+        async fn is_token_revoked(&self, jti: &str) -> anyhow::Result<bool> {
+            self.revoked_tokens.is_revoked(jti).await
+        }
+    }
+
+    fn new_state(redis_url: &str) -> TestState {
+        TestState {
+            decoding_key: DecodingKey::load(include_str!("../../fixtures/keys/ed25519-public.pem"))
+                .unwrap(),
+            revoked_tokens: RevokedTokens::new(redis::Client::open(redis_url).unwrap()),
+        }
+    }
+
+    fn sign_token() -> String {
+        let key =
+            EncodingKey::load(include_str!("../../fixtures/keys/ed25519-private.pem")).unwrap();
+        key.sign(CurUser {
+            id: 1,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    async fn request(state: TestState, token: &str) -> StatusCode {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(from_fn_with_state(state, verify_token::<TestState>));
+        let req = Request::builder()
+            .uri("/")
+            .header("Authorization", format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_redis_verify_revoked_token() {
+        let state = new_state("redis://localhost:6379");
+        let token = sign_token();
+        assert_eq!(request(state.clone(), &token).await, StatusCode::OK);
+
+        // 登出后同一个token不能再访问
+        let verified = state.decoding_key.decode(&token).unwrap();
+        state
+            .revoked_tokens
+            .revoke(&verified.jti, verified.remaining_secs())
+            .await
+            .unwrap();
+        assert_eq!(request(state, &token).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_verify_token_fails_closed() {
+        // redis不可用时无法确认token是否已吊销，拒绝请求
+        let state = new_state("redis://127.0.0.1:1");
+        assert_eq!(
+            request(state.clone(), &sign_token()).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(request(state, "invalid").await, StatusCode::UNAUTHORIZED);
+    }
+}
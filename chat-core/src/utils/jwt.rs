@@ -1,17 +1,42 @@
+use std::fmt;
+
 use crate::models::user::CurUser;
 use anyhow::Result;
 use jwt_simple::{
     claims::Claims,
-    prelude::{Duration, Ed25519KeyPair, Ed25519PublicKey, EdDSAKeyPairLike, EdDSAPublicKeyLike},
+    prelude::{
+        Clock, Duration, Ed25519KeyPair, Ed25519PublicKey, EdDSAKeyPairLike, EdDSAPublicKeyLike,
+    },
+    token::Token,
 };
 
-const JWT_DURATION: u64 = 60 * 60 * 24 * 7;
+// 访问令牌有效期较短，过期后使用刷新令牌换取新的访问令牌
+pub const JWT_DURATION: u64 = 60 * 15;
 const JWT_ISSUER: &str = "chat-server";
 const JWT_AUD: &str = "chat-web";
 
+#[derive(Clone)]
 pub struct EncodingKey(Ed25519KeyPair);
 
-pub struct DecodingKey(Ed25519PublicKey);
+// 验证用的公钥集合，根据token头部的kid选择公钥，轮换签名密钥期间新旧公钥同时生效
+#[derive(Clone)]
+pub struct DecodingKey(Vec<Ed25519PublicKey>);
+
+// 验证通过的token信息，jti用于登出时吊销token
+#[derive(Debug, Clone)]
+pub struct VerifiedToken {
+    pub user: CurUser,
+    pub jti: String,
+    pub expires_at: u64,
+}
+
+impl VerifiedToken {
+    // token剩余的有效时间（秒）
+    pub fn remaining_secs(&self) -> u64 {
+        self.expires_at
+            .saturating_sub(Clock::now_since_epoch().as_secs())
+    }
+}
 
 impl EncodingKey {
     pub fn load(pem: &str) -> Result<Self, jwt_simple::Error> {
@@ -19,10 +44,16 @@ impl EncodingKey {
         Ok(Self(key_pair))
     }
 
+    // 签发的token头部会带上kid
+    pub fn with_key_id(self, kid: &str) -> Self {
+        Self(self.0.with_key_id(kid))
+    }
+
     pub fn sign(&self, user: impl Into<CurUser>) -> Result<String, jwt_simple::Error> {
         let claims = Claims::with_custom_claims(user.into(), Duration::from_secs(JWT_DURATION))
             .with_issuer(JWT_ISSUER)
-            .with_audience(JWT_AUD);
+            .with_audience(JWT_AUD)
+            .with_jwt_id(uuid::Uuid::now_v7());
 
         self.0.sign(claims)
     }
@@ -31,12 +62,72 @@ impl EncodingKey {
 impl DecodingKey {
     pub fn load(pem: &str) -> Result<Self, jwt_simple::Error> {
         let key = Ed25519PublicKey::from_pem(pem)?;
-        Ok(Self(key))
+        Ok(Self(vec![key]))
+    }
+
+    // 加载多个公钥，每个公钥对应一个kid
+    pub fn load_set<'a>(
+        keys: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, jwt_simple::Error> {
+        let keys = keys
+            .into_iter()
+            .map(|(kid, pem)| Ok(Ed25519PublicKey::from_pem(pem)?.with_key_id(kid)))
+            .collect::<Result<Vec<_>, jwt_simple::Error>>()?;
+        if keys.is_empty() {
+            return Err(jwt_simple::Error::msg("no public key configured"));
+        }
+        Ok(Self(keys))
     }
 
     pub fn verify(&self, token: &str) -> Result<CurUser, jwt_simple::Error> {
-        let claims = self.0.verify_token::<CurUser>(token, None)?;
-        Ok(claims.custom)
+        Ok(self.decode(token)?.user)
+    }
+
+    // 验证token并返回jti和过期时间，没有jti的token无法吊销，直接拒绝
+    pub fn decode(&self, token: &str) -> Result<VerifiedToken, jwt_simple::Error> {
+        let claims = self.select(token)?.verify_token::<CurUser>(token, None)?;
+        let jti = claims
+            .jwt_id
+            .ok_or_else(|| jwt_simple::Error::msg("token has no jti"))?;
+        let expires_at = claims
+            .expires_at
+            .ok_or_else(|| jwt_simple::Error::msg("token has no exp"))?
+            .as_secs();
+        Ok(VerifiedToken {
+            user: claims.custom,
+            jti,
+            expires_at,
+        })
+    }
+
+    // 根据token头部的kid选择公钥，只配置了一个公钥时兼容没有kid的token
+    fn select(&self, token: &str) -> Result<&Ed25519PublicKey, jwt_simple::Error> {
+        let metadata = Token::decode_metadata(token)?;
+        match metadata.key_id() {
+            Some(kid) => self
+                .0
+                .iter()
+                .find(|key| key.key_id().as_deref() == Some(kid))
+                .ok_or_else(|| jwt_simple::Error::msg(format!("unknown kid: {}", kid))),
+            None if self.0.len() == 1 => Ok(&self.0[0]),
+            None => Err(jwt_simple::Error::msg("token has no kid")),
+        }
+    }
+}
+
+impl fmt::Debug for EncodingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncodingKey")
+            .field("kid", self.0.key_id())
+            .finish()
+    }
+}
+
+impl fmt::Debug for DecodingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|key| key.key_id()))
+            .finish()
     }
 }
 /**
@@ -67,5 +158,38 @@ mod tests {
         assert_eq!(user.ws_id, 1);
         assert_eq!(user.fullname, "test");
         assert_eq!(user.email, "abc@gmail.com");
+
+        let verified = decoding_key.decode(&token).unwrap();
+        assert!(!verified.jti.is_empty());
+        assert!(verified.remaining_secs() > 0);
+    }
+
+    #[test]
+    fn test_jwt_key_rotation() {
+        let old_key = Ed25519KeyPair::generate();
+        let new_key = Ed25519KeyPair::generate();
+        let old_pem = old_key.public_key().to_pem();
+        let new_pem = new_key.public_key().to_pem();
+        let decoding_key = DecodingKey::load_set([("old", &*old_pem), ("new", &*new_pem)]).unwrap();
+
+        let user = CurUser {
+            id: 1,
+            ..Default::default()
+        };
+        let old_token = EncodingKey(old_key)
+            .with_key_id("old")
+            .sign(user.clone())
+            .unwrap();
+        let new_token = EncodingKey(new_key)
+            .with_key_id("new")
+            .sign(user.clone())
+            .unwrap();
+        assert_eq!(decoding_key.verify(&old_token).unwrap().id, 1);
+        assert_eq!(decoding_key.verify(&new_token).unwrap().id, 1);
+
+        // 旧公钥下线后，旧token不再有效
+        let decoding_key = DecodingKey::load_set([("new", &*new_pem)]).unwrap();
+        assert!(decoding_key.verify(&old_token).is_err());
+        assert!(decoding_key.verify(&new_token).is_ok());
     }
 }
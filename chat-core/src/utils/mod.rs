@@ -1,2 +1,3 @@
 pub mod jwt;
 pub mod redis_lock;
+pub mod revoked_token;
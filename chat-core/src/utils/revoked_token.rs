@@ -0,0 +1,78 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+
+const REVOKED_TOKEN_PREFIX: &str = "revoked_token:";
+
+// 已吊销token的黑名单，key为jti，过期时间与token剩余有效期一致
+#[derive(Debug, Clone)]
+pub struct RevokedTokens {
+    redis_client: redis::Client,
+}
+
+impl RevokedTokens {
+    pub fn new(redis_client: redis::Client) -> Self {
+        Self { redis_client }
+    }
+
+    /// 吊销token，ttl_secs为token的剩余有效期
+    pub async fn revoke(&self, jti: &str, ttl_secs: u64) -> Result<()> {
+        // token已经过期，不需要再记录
+        if ttl_secs == 0 {
+            return Ok(());
+        }
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let _: () = conn.set_ex(Self::key(jti), 1, ttl_secs).await?;
+        Ok(())
+    }
+
+    /// 判断token是否已被吊销
+    pub async fn is_revoked(&self, jti: &str) -> Result<bool> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let exists: bool = conn.exists(Self::key(jti)).await?;
+        Ok(exists)
+    }
+
+    fn key(jti: &str) -> String {
+        format!("{}{}", REVOKED_TOKEN_PREFIX, jti)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REDIS_URL: &str = "redis://localhost:6379";
+
+    #[tokio::test]
+    async fn test_redis_revoked_tokens() {
+        let revoked_tokens = RevokedTokens::new(redis::Client::open(REDIS_URL).unwrap());
+        let jti = uuid::Uuid::now_v7().to_string();
+        assert!(!revoked_tokens.is_revoked(&jti).await.unwrap());
+
+        revoked_tokens.revoke(&jti, 60).await.unwrap();
+        assert!(revoked_tokens.is_revoked(&jti).await.unwrap());
+
+        // 黑名单的过期时间和token剩余有效期一致
+        let mut conn = revoked_tokens
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap();
+        let ttl: i64 = conn.ttl(RevokedTokens::key(&jti)).await.unwrap();
+        assert!(ttl > 0 && ttl <= 60);
+
+        // 已经过期的token不需要记录
+        let expired = uuid::Uuid::now_v7().to_string();
+        revoked_tokens.revoke(&expired, 0).await.unwrap();
+        assert!(!revoked_tokens.is_revoked(&expired).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_revoked_tokens_unavailable() {
+        // redis不可用时返回错误，由调用方决定拒绝请求
+        let revoked_tokens =
+            RevokedTokens::new(redis::Client::open("redis://127.0.0.1:1").unwrap());
+        assert!(revoked_tokens.is_revoked("jti").await.is_err());
+        assert!(revoked_tokens.revoke("jti", 60).await.is_err());
+    }
+}
@@ -1,21 +1,23 @@
-use axum::response::IntoResponse;
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tracing::error;
+
+use crate::middlewares::request_id::current_request_id;
 
 #[derive(Debug, Error)]
 pub(crate) enum AppError {
-    // TODO: 更好的处理anyhow的错误
-    // #[error("service error")]
-    // ServiceError(#[from] anyhow::Error),
-    #[error("jwt error")]
-    JwtError(#[from] jwt_simple::Error),
+    // 内部错误只记录日志，不把细节返回给客户端
+    #[error("service error: {0}")]
+    ServiceError(#[from] anyhow::Error),
 
-    #[error("db execute error")]
+    #[error("db execute error: {0}")]
     DbError(#[from] sqlx::Error),
 
-    #[error("password hash error")]
+    #[error("password hash error: {0}")]
     PasswordHashError(#[from] argon2::password_hash::Error),
 
-    #[error("sedre error")]
+    #[error("sedre error: {0}")]
     SedreError(#[from] serde_json::Error),
 
     #[error("chat member is empty")]
@@ -41,14 +43,201 @@ pub(crate) enum AppError {
 
     #[error("email or password incorrect")]
     EmailOrPasswordIncorrect,
+
+    #[error("refresh token invalid")]
+    RefreshTokenInvalid,
+
+    #[error("refresh token reused")]
+    RefreshTokenReused,
+
+    #[error("permission denied")]
+    PermissionDenied,
+
+    #[error("file too large")]
+    FileTooLarge,
+
+    #[error("file type not allowed: {0}")]
+    FileTypeNotAllowed(String),
+
+    #[error("invalid upload: {0}")]
+    InvalidUpload(String),
+
+    #[error("invalid attachment: {0}")]
+    InvalidAttachment(String),
+
+    #[error("file not found")]
+    FileNotFound,
+
+    #[error("upload session not found")]
+    UploadSessionNotFound,
+
+    #[error("storage quota exceeded")]
+    StorageQuotaExceeded,
+
+    #[error("message not found")]
+    MessageNotFound,
+
+    #[error("message edit window expired")]
+    MessageEditWindowExpired,
+
+    #[error("invalid parent message")]
+    InvalidParentMessage,
+
+    #[error("invalid reaction: {0}")]
+    InvalidReaction(String),
+
+    #[error("invalid cursor")]
+    InvalidCursor,
+
+    #[error("invalid client message id")]
+    InvalidClientMsgId,
+
+    #[error("invalid message: {0}")]
+    InvalidMessage(String),
+
+    #[error("invalid search query")]
+    InvalidSearchQuery,
+
+    #[error("pinned message limit exceeded")]
+    PinLimitExceeded,
+}
+
+// 错误响应体，code 是稳定的机器可读错误码，前端据此做本地化
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ErrorOutput {
+    pub code: String,
+    pub message: String,
+    pub request_id: Option<String>,
+}
+
+impl AppError {
+    pub(crate) fn status(&self) -> StatusCode {
+        match self {
+            AppError::ServiceError(_)
+            | AppError::DbError(_)
+            | AppError::PasswordHashError(_)
+            | AppError::SedreError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::ChatMemberIsEmpty
+            | AppError::InvalidUpload(_)
+            | AppError::InvalidAttachment(_)
+            | AppError::InvalidParentMessage
+            | AppError::InvalidReaction(_)
+            | AppError::InvalidCursor
+            | AppError::InvalidClientMsgId
+            | AppError::InvalidMessage(_)
+            | AppError::InvalidSearchQuery => StatusCode::BAD_REQUEST,
+            AppError::EmailOrPasswordIncorrect
+            | AppError::RefreshTokenInvalid
+            | AppError::RefreshTokenReused => StatusCode::UNAUTHORIZED,
+            AppError::UserNotInChat
+            | AppError::PermissionDenied
+            | AppError::MessageEditWindowExpired => StatusCode::FORBIDDEN,
+            AppError::UserNotFound
+            | AppError::ChatNotFound
+            | AppError::WorkspaceNotFound
+            | AppError::FileNotFound
+            | AppError::UploadSessionNotFound
+            | AppError::MessageNotFound => StatusCode::NOT_FOUND,
+            AppError::EmailAlreadyExists
+            | AppError::WorkspaceNameAlreadyExists
+            | AppError::PinLimitExceeded => StatusCode::CONFLICT,
+            AppError::FileTooLarge | AppError::StorageQuotaExceeded => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            AppError::FileTypeNotAllowed(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        }
+    }
+
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            AppError::ServiceError(_)
+            | AppError::DbError(_)
+            | AppError::PasswordHashError(_)
+            | AppError::SedreError(_) => "internal_error",
+            AppError::ChatMemberIsEmpty => "chat_member_is_empty",
+            AppError::UserNotFound => "user_not_found",
+            AppError::ChatNotFound => "chat_not_found",
+            AppError::EmailAlreadyExists => "email_already_exists",
+            AppError::UserNotInChat => "user_not_in_chat",
+            AppError::WorkspaceNameAlreadyExists => "workspace_name_already_exists",
+            AppError::WorkspaceNotFound => "workspace_not_found",
+            AppError::EmailOrPasswordIncorrect => "email_or_password_incorrect",
+            AppError::RefreshTokenInvalid => "refresh_token_invalid",
+            AppError::RefreshTokenReused => "refresh_token_reused",
+            AppError::PermissionDenied => "permission_denied",
+            AppError::FileTooLarge => "file_too_large",
+            AppError::FileTypeNotAllowed(_) => "file_type_not_allowed",
+            AppError::InvalidUpload(_) => "invalid_upload",
+            AppError::InvalidAttachment(_) => "invalid_attachment",
+            AppError::FileNotFound => "file_not_found",
+            AppError::UploadSessionNotFound => "upload_session_not_found",
+            AppError::StorageQuotaExceeded => "storage_quota_exceeded",
+            AppError::MessageNotFound => "message_not_found",
+            AppError::MessageEditWindowExpired => "message_edit_window_expired",
+            AppError::InvalidParentMessage => "invalid_parent_message",
+            AppError::InvalidReaction(_) => "invalid_reaction",
+            AppError::InvalidCursor => "invalid_cursor",
+            AppError::InvalidClientMsgId => "invalid_client_msg_id",
+            AppError::InvalidMessage(_) => "invalid_message",
+            AppError::InvalidSearchQuery => "invalid_search_query",
+            AppError::PinLimitExceeded => "pin_limit_exceeded",
+        }
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
-        (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            format!("{}", self),
-        )
-            .into_response()
+        let status = self.status();
+        let request_id = current_request_id();
+        let message = if status.is_server_error() {
+            error!("request {:?} failed: {:?}", request_id, self);
+            "internal server error".to_string()
+        } else {
+            self.to_string()
+        };
+        let output = ErrorOutput {
+            code: self.code().to_string(),
+            message,
+            request_id,
+        };
+        (status, Json(output)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middlewares::request_id::REQUEST_ID;
+
+    async fn to_output(err: AppError) -> (StatusCode, ErrorOutput) {
+        let res = REQUEST_ID
+            .scope("test-request-id".to_string(), async { err.into_response() })
+            .await;
+        let status = res.status();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_error_output() {
+        let (status, output) = to_output(AppError::ChatNotFound).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(output.code, "chat_not_found");
+        assert_eq!(output.request_id.as_deref(), Some("test-request-id"));
+
+        let (status, output) = to_output(AppError::EmailAlreadyExists).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(output.code, "email_already_exists");
+    }
+
+    #[tokio::test]
+    async fn test_service_error_is_hidden() {
+        let err = AppError::ServiceError(anyhow::anyhow!("connection refused"));
+        let (status, output) = to_output(err).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(output.code, "internal_error");
+        assert_eq!(output.message, "internal server error");
     }
 }
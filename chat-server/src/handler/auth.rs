@@ -1,9 +1,9 @@
 use anyhow::Result;
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Extension, Json};
-use chat_core::{models::user::CurUser, utils::jwt::EncodingKey};
-use serde::{Deserialize, Serialize};
+use chat_core::utils::jwt::VerifiedToken;
+use serde::Deserialize;
 
-use crate::{error::AppError, models::user::User, AppState};
+use crate::{error::AppError, AppState};
 
 #[derive(Debug, Clone, Deserialize)]
 pub(crate) struct SigninPayload {
@@ -11,14 +11,14 @@ pub(crate) struct SigninPayload {
     pub password: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub(crate) struct SigninResp {
-    pub token: String,
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct RefreshPayload {
+    pub refresh_token: String,
 }
-impl SigninResp {
-    pub fn new(token: String) -> Self {
-        Self { token }
-    }
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct LogoutPayload {
+    pub refresh_token: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -33,22 +33,19 @@ pub(crate) async fn signin(
     State(state): State<AppState>,
     Json(payload): Json<SigninPayload>,
 ) -> Result<impl IntoResponse, AppError> {
-    let user = state.user_service.find_by_email(&payload.email).await?;
-    if user.is_none() {
-        return Err(AppError::EmailOrPasswordIncorrect);
-    }
-    let user = user.unwrap();
-    // 验证密码
-    if !User::verify_password(&payload.password, &user.password)? {
-        return Err(AppError::EmailOrPasswordIncorrect);
-    }
-    // 使用jwt生成token，包含用户数据
-    let encoding_key = EncodingKey::load(state.app_config.auth.secret_key.as_str())?;
-
-    let user: CurUser = user.into();
-    let token = encoding_key.sign(user)?;
+    let token_pair = state
+        .auth_service
+        .signin(&payload.email, &payload.password)
+        .await?;
+    Ok((StatusCode::OK, Json(token_pair)).into_response())
+}
 
-    Ok((StatusCode::OK, Json(SigninResp::new(token))).into_response())
+pub(crate) async fn refresh(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let token_pair = state.auth_service.refresh(&payload.refresh_token).await?;
+    Ok((StatusCode::OK, Json(token_pair)).into_response())
 }
 
 pub(crate) async fn signup(
@@ -68,9 +65,21 @@ pub(crate) async fn signup(
 }
 
 pub(crate) async fn logout(
-    Extension(_user): Extension<CurUser>,
-    State(_state): State<AppState>,
+    Extension(token): Extension<VerifiedToken>,
+    State(state): State<AppState>,
+    payload: Option<Json<LogoutPayload>>,
 ) -> Result<impl IntoResponse, AppError> {
-    // 用户登出，删除token
-    Ok(())
+    // 同时吊销刷新令牌，避免登出后还能换取新的访问令牌
+    if let Some(refresh_token) = payload.and_then(|Json(p)| p.refresh_token) {
+        state
+            .auth_service
+            .revoke(&token.user, &refresh_token)
+            .await?;
+    }
+    // 用户登出，将token加入黑名单，直到token自然过期
+    state
+        .revoked_tokens
+        .revoke(&token.jti, token.remaining_secs())
+        .await?;
+    Ok(StatusCode::OK)
 }
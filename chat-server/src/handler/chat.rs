@@ -4,6 +4,8 @@ use axum::{extract::State, http::StatusCode, response::IntoResponse, Extension,
 use chat_core::{chat_type::ChatType, models::user::CurUser};
 use serde::Deserialize;
 
+use crate::models::role::ChatRole;
+
 #[derive(Debug, Clone, Deserialize)]
 pub(crate) struct ChatCreate {
     pub title: String,
@@ -26,33 +28,138 @@ pub(crate) struct ChatLeave {
     pub chat_id: i64,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ChatRename {
+    pub chat_id: i64,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ChatKick {
+    pub chat_id: i64,
+    pub user_ids: Vec<i64>,
+}
+
+// 聊天列表每页的默认数量
+const DEFAULT_CHAT_PAGE_SIZE: i64 = 50;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct ChatListQuery {
+    // 上一页返回的 next_cursor
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ChatMarkRead {
+    pub chat_id: i64,
+    // 不传时标记全部已读
+    pub message_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ChatSetRole {
+    pub chat_id: i64,
+    pub user_id: i64,
+    pub role: ChatRole,
+}
+
 pub(crate) async fn create(
-    Extension(_user): Extension<CurUser>,
+    Extension(user): Extension<CurUser>,
     State(state): State<AppState>,
     Json(chat_create): Json<ChatCreate>,
 ) -> Result<impl IntoResponse, AppError> {
     let chat_id = state
         .chat_service
-        .create(chat_create.title, chat_create.r#type, chat_create.members)
+        .create(
+            &user,
+            chat_create.title,
+            chat_create.r#type,
+            chat_create.members,
+        )
         .await?;
     Ok((StatusCode::CREATED, Json(chat_id)))
 }
 
 pub(crate) async fn drop(
-    Extension(_user): Extension<CurUser>,
+    Extension(user): Extension<CurUser>,
     State(state): State<AppState>,
     Json(chat_drop): Json<ChatDrop>,
 ) -> Result<impl IntoResponse, AppError> {
-    state.chat_service.delete(chat_drop.chat_id).await?;
+    state.chat_service.delete(&user, chat_drop.chat_id).await?;
     Ok((StatusCode::CREATED, Json(true)))
 }
 
+pub(crate) async fn rename(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(chat_rename): Json<ChatRename>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .chat_service
+        .rename(&user, chat_rename.chat_id, chat_rename.title)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+pub(crate) async fn kick(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(chat_kick): Json<ChatKick>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .chat_service
+        .kick(&user, chat_kick.chat_id, chat_kick.user_ids)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+pub(crate) async fn set_role(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(chat_set_role): Json<ChatSetRole>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .chat_service
+        .set_member_role(
+            &user,
+            chat_set_role.chat_id,
+            chat_set_role.user_id,
+            chat_set_role.role,
+        )
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+// 聊天列表，按最后活跃时间倒序分页，不传参数时返回第一页
 pub(crate) async fn list_by_user_id(
     Extension(user): Extension<CurUser>,
     State(state): State<AppState>,
+    query: Option<Json<ChatListQuery>>,
+) -> Result<impl IntoResponse, AppError> {
+    let query = query.map(|Json(q)| q).unwrap_or_default();
+    let page = state
+        .chat_service
+        .list_by_user_id(
+            &user,
+            query.cursor,
+            query.limit.unwrap_or(DEFAULT_CHAT_PAGE_SIZE),
+        )
+        .await?;
+    Ok(Json(page))
+}
+
+// 标记已读，返回最新的已读位置
+pub(crate) async fn mark_read(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(chat_mark_read): Json<ChatMarkRead>,
 ) -> Result<impl IntoResponse, AppError> {
-    let chats = state.chat_service.list_by_user_id(user.id).await?;
-    Ok(Json(chats))
+    let last_read_message_id = state
+        .chat_service
+        .mark_read(&user, chat_mark_read.chat_id, chat_mark_read.message_id)
+        .await?;
+    Ok(Json(last_read_message_id))
 }
 
 pub(crate) async fn user_join(
@@ -62,7 +169,7 @@ pub(crate) async fn user_join(
 ) -> Result<impl IntoResponse, AppError> {
     state
         .chat_service
-        .add_members(chat_join.chat_id, vec![user.id])
+        .add_members(&user, chat_join.chat_id, vec![user.id])
         .await?;
     Ok(StatusCode::OK)
 }
@@ -74,7 +181,7 @@ pub(crate) async fn user_leave(
 ) -> Result<impl IntoResponse, AppError> {
     state
         .chat_service
-        .remove_members(chat_leave.chat_id, vec![user.id])
+        .remove_members(&user, chat_leave.chat_id, vec![user.id])
         .await?;
     Ok(StatusCode::OK)
 }
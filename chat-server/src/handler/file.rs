@@ -0,0 +1,244 @@
+use crate::{error::AppError, storage, AppState};
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Extension,
+};
+use chat_core::models::user::CurUser;
+use tokio_util::io::ReaderStream;
+
+// 解析后的 Range 请求
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteRange {
+    // 没有 Range 或者无法处理的 Range（比如多段），返回完整文件
+    Full,
+    // 闭区间 [start, end]
+    Partial(u64, u64),
+    // 范围超出文件大小
+    Unsatisfiable,
+}
+
+// 下载文件，只有引用了该文件的聊天室成员才能下载
+pub(crate) async fn download(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let upload = state.message_service.find_file(&user, &key).await?;
+    let disposition = content_disposition(&upload.filename, &upload.mime_type);
+    // 对象存储直接跳转到预签名地址，由存储处理 Range 和缓存
+    if let Some(url) = state
+        .message_service
+        .presign_download(&upload, &disposition)
+        .await?
+    {
+        return Ok(Redirect::temporary(&url).into_response());
+    }
+    let size = upload.size as u64;
+    let etag = storage::etag_for_key(&upload.storage_key);
+
+    // 文件按内容寻址，内容不会变化，可以长期缓存
+    let mut resp_headers = HeaderMap::new();
+    resp_headers.insert(header::ETAG, header_value(&etag));
+    resp_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    resp_headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("private, max-age=31536000, immutable"),
+    );
+
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        if etag_matches(if_none_match.to_str().unwrap_or_default(), &etag) {
+            return Ok((StatusCode::NOT_MODIFIED, resp_headers).into_response());
+        }
+    }
+
+    resp_headers.insert(header::CONTENT_TYPE, header_value(&upload.mime_type));
+    resp_headers.insert(header::CONTENT_DISPOSITION, header_value(&disposition));
+
+    // If-Range 不匹配时忽略 Range，返回完整文件
+    let range = match headers.get(header::RANGE) {
+        Some(range)
+            if headers
+                .get(header::IF_RANGE)
+                .is_none_or(|v| v.to_str().unwrap_or_default() == etag) =>
+        {
+            parse_range(range.to_str().unwrap_or_default(), size)
+        }
+        _ => ByteRange::Full,
+    };
+
+    match range {
+        ByteRange::Full => {
+            let reader = state
+                .message_service
+                .read_file(&upload.storage_key, 0, size)
+                .await?;
+            resp_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(size));
+            let body = Body::from_stream(ReaderStream::new(reader));
+            Ok((StatusCode::OK, resp_headers, body).into_response())
+        }
+        ByteRange::Partial(start, end) => {
+            let len = end - start + 1;
+            let reader = state
+                .message_service
+                .read_file(&upload.storage_key, start, len)
+                .await?;
+            resp_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+            resp_headers.insert(
+                header::CONTENT_RANGE,
+                header_value(&format!("bytes {}-{}/{}", start, end, size)),
+            );
+            let body = Body::from_stream(ReaderStream::new(reader));
+            Ok((StatusCode::PARTIAL_CONTENT, resp_headers, body).into_response())
+        }
+        ByteRange::Unsatisfiable => {
+            resp_headers.remove(header::CONTENT_DISPOSITION);
+            resp_headers.insert(
+                header::CONTENT_RANGE,
+                header_value(&format!("bytes */{}", size)),
+            );
+            Ok((StatusCode::RANGE_NOT_SATISFIABLE, resp_headers).into_response())
+        }
+    }
+}
+
+// 解析 Range 头，只支持单段的 bytes 范围
+pub(crate) fn parse_range(value: &str, size: u64) -> ByteRange {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+    let range = if start.is_empty() {
+        // bytes=-N 表示最后N个字节
+        match end.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(n) => (size.saturating_sub(n), size.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        }
+    } else {
+        let Ok(start) = start.parse::<u64>() else {
+            return ByteRange::Full;
+        };
+        let end = if end.is_empty() {
+            size.saturating_sub(1)
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => end.min(size.saturating_sub(1)),
+                _ => return ByteRange::Full,
+            }
+        };
+        (start, end)
+    };
+    if size == 0 || range.0 >= size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(range.0, range.1)
+}
+
+// If-None-Match 可能是 * 或者逗号分隔的多个 ETag，弱比较
+fn etag_matches(value: &str, etag: &str) -> bool {
+    value
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+// 图片、音视频、PDF 在浏览器中直接打开，其他文件下载
+// filename 给老客户端一个 ASCII 的文件名，filename* 按 RFC 5987 编码原始文件名
+pub(crate) fn content_disposition(filename: &str, mime_type: &str) -> String {
+    let inline = ["image/", "video/", "audio/"]
+        .iter()
+        .any(|prefix| mime_type.starts_with(prefix))
+        || mime_type == "application/pdf";
+    let disposition = if inline { "inline" } else { "attachment" };
+    let fallback: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && c != '"' && c != '\\' || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let encoded: String = filename
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect();
+    format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        disposition, fallback, encoded
+    )
+}
+
+fn header_value(value: &str) -> HeaderValue {
+    HeaderValue::from_str(value).unwrap_or_else(|_| HeaderValue::from_static(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), ByteRange::Partial(0, 99));
+        assert_eq!(
+            parse_range("bytes=100-", 1000),
+            ByteRange::Partial(100, 999)
+        );
+        assert_eq!(
+            parse_range("bytes=-100", 1000),
+            ByteRange::Partial(900, 999)
+        );
+        assert_eq!(parse_range("bytes=-2000", 1000), ByteRange::Partial(0, 999));
+        assert_eq!(
+            parse_range("bytes=900-2000", 1000),
+            ByteRange::Partial(900, 999)
+        );
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=5-1", 1000), ByteRange::Full);
+        assert_eq!(parse_range("items=0-1", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=a-b", 1000), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        assert!(etag_matches("\"abc\"", "\"abc\""));
+        assert!(etag_matches("W/\"abc\"", "\"abc\""));
+        assert!(etag_matches("\"x\", \"abc\"", "\"abc\""));
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(!etag_matches("\"x\"", "\"abc\""));
+    }
+
+    #[test]
+    fn test_content_disposition() {
+        assert_eq!(
+            content_disposition("a b.png", "image/png"),
+            "inline; filename=\"a b.png\"; filename*=UTF-8''a%20b.png"
+        );
+        assert_eq!(
+            content_disposition("报告.zip", "application/zip"),
+            "attachment; filename=\"__.zip\"; filename*=UTF-8''%E6%8A%A5%E5%91%8A.zip"
+        );
+        assert!(HeaderValue::from_str(&content_disposition("a\"\\\n.txt", "text/plain")).is_ok());
+    }
+}
@@ -1,40 +1,161 @@
-use crate::{error::AppError, AppState};
+use crate::{
+    error::AppError,
+    models::{
+        message::{HistoryCursor, NewMessage},
+        message_search::MessageSearch,
+    },
+    AppState,
+};
 use anyhow::Result;
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Extension, Json};
-use chat_core::models::user::CurUser;
+use axum::{
+    body::Bytes,
+    extract::{Multipart, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chat_core::{
+    message_kind::{MessageKind, MessagePayload},
+    models::user::CurUser,
+};
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use tracing::info;
 
-#[derive(Debug, Clone, Deserialize)]
+// 消息列表每页的默认数量
+const DEFAULT_PAGE_SIZE: i64 = 50;
+
+// before、after、around 最多只能传一个，都不传时返回最新的消息，消息ID必须大于0
+#[derive(Debug, Clone, Default, Deserialize)]
 pub(crate) struct MessageQuery {
     pub chat_id: i64,
-    pub limit: i64,
-    pub start_message_id: i64,
+    pub limit: Option<i64>,
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+    pub around: Option<i64>,
+}
+
+impl MessageQuery {
+    pub(crate) fn cursor(&self) -> Result<HistoryCursor, AppError> {
+        if [self.before, self.after, self.around]
+            .iter()
+            .flatten()
+            .any(|id| *id <= 0)
+        {
+            return Err(AppError::InvalidCursor);
+        }
+        match (self.before, self.after, self.around) {
+            (None, None, None) => Ok(HistoryCursor::Latest),
+            (Some(id), None, None) => Ok(HistoryCursor::Before(id)),
+            (None, Some(id), None) => Ok(HistoryCursor::After(id)),
+            (None, None, Some(id)) => Ok(HistoryCursor::Around(id)),
+            _ => Err(AppError::InvalidCursor),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub(crate) struct MessageSend {
     pub chat_id: i64,
+    // 不传时是文本消息
+    #[serde(default)]
+    pub kind: MessageKind,
+    #[serde(default)]
     pub content: String,
+    // 卡片等结构化内容
+    pub payload: Option<MessagePayload>,
     pub attachments: Option<Vec<String>>,
+    // 话题回复的父消息
+    pub parent_message_id: Option<i64>,
+    // 客户端生成的消息ID，重试发送时带上相同的ID
+    pub client_msg_id: Option<String>,
+}
+
+// 话题回复按发送顺序分页，after 为上一页最后一条回复的ID，不传时从第一条开始
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ThreadQuery {
+    pub message_id: i64,
+    pub limit: Option<i64>,
+    pub after: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct MessageEditPayload {
+    pub message_id: i64,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct MessageIdPayload {
+    pub message_id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ReactionPayload {
+    pub message_id: i64,
+    pub emoji: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct MentionQuery {
+    pub limit: i64,
+    pub start_message_id: i64,
+}
+
+// 搜索条件都是可选的，不传 chat_id 时搜索所有所在的聊天室
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct SearchQuery {
+    pub query: String,
+    pub chat_id: Option<i64>,
+    pub sender_id: Option<i64>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub has_attachment: Option<bool>,
+    // 上一页返回的 next_cursor
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ChatIdPayload {
+    pub chat_id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct PresignUpload {
+    pub chat_id: i64,
+    pub filename: String,
+    pub mime_type: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ConfirmUpload {
+    pub upload_id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct UploadSessionQuery {
+    pub session_id: String,
 }
 
 pub(crate) async fn recent(
-    Extension(_user): Extension<CurUser>,
+    Extension(user): Extension<CurUser>,
     State(state): State<AppState>,
     Json(message_query): Json<MessageQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("recent");
-    let messages = state
+    let page = state
         .message_service
         .recent(
+            &user,
             message_query.chat_id,
-            message_query.start_message_id,
-            message_query.limit,
+            message_query.cursor()?,
+            message_query.limit.unwrap_or(DEFAULT_PAGE_SIZE),
         )
         .await?;
-    info!("recent messages: {:?}", messages);
-    Ok(Json(messages))
+    info!("recent messages: {:?}", page.messages);
+    Ok(Json(page))
 }
 
 pub(crate) async fn send(
@@ -42,19 +163,367 @@ pub(crate) async fn send(
     State(state): State<AppState>,
     Json(message_send): Json<MessageSend>,
 ) -> Result<impl IntoResponse, AppError> {
-    state
+    let sent = state
         .message_service
         .send(
+            &user,
             message_send.chat_id,
-            user.id,
-            message_send.content,
-            message_send.attachments,
+            NewMessage {
+                kind: message_send.kind,
+                content: message_send.content,
+                payload: message_send.payload,
+                attachments: message_send.attachments.unwrap_or_default(),
+                parent_message_id: message_send.parent_message_id,
+                client_msg_id: message_send.client_msg_id,
+            },
         )
         .await?;
 
+    Ok(Json(sent))
+}
+
+// 分页查询话题的回复
+pub(crate) async fn thread(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(query): Json<ThreadQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    if query.after.is_some_and(|id| id <= 0) {
+        return Err(AppError::InvalidCursor);
+    }
+    let page = state
+        .message_service
+        .thread(
+            &user,
+            query.message_id,
+            query.after,
+            query.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        )
+        .await?;
+    Ok(Json(page))
+}
+
+// 编辑消息
+pub(crate) async fn edit(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<MessageEditPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .message_service
+        .edit(&user, payload.message_id, payload.content)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+// 删除消息
+pub(crate) async fn delete(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<MessageIdPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .message_service
+        .delete(&user, payload.message_id)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+// 查询消息的编辑历史
+pub(crate) async fn history(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<MessageIdPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let edits = state
+        .message_service
+        .history(&user, payload.message_id)
+        .await?;
+    Ok(Json(edits))
+}
+
+// 置顶消息
+pub(crate) async fn pin(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<MessageIdPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .message_service
+        .pin(&user, payload.message_id, true)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+// 取消置顶消息
+pub(crate) async fn unpin(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<MessageIdPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .message_service
+        .pin(&user, payload.message_id, false)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+// 查询聊天室置顶的消息
+pub(crate) async fn pins(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<ChatIdPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let pins = state.message_service.pins(&user, payload.chat_id).await?;
+    Ok(Json(pins))
+}
+
+// 全文搜索消息
+pub(crate) async fn search(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(query): Json<SearchQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let search = MessageSearch {
+        terms: MessageSearch::parse_terms(&query.query),
+        chat_id: query.chat_id,
+        sender_id: query.sender_id,
+        from: query.from,
+        to: query.to,
+        has_attachment: query.has_attachment,
+        before: query.cursor,
+    };
+    let page = state
+        .message_service
+        .search(&user, search, query.limit.unwrap_or(DEFAULT_PAGE_SIZE))
+        .await?;
+    Ok(Json(page))
+}
+
+// 查询当前用户在所有聊天室中未读的提及
+pub(crate) async fn unread_mentions(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(query): Json<MentionQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let mentions = state
+        .message_service
+        .unread_mentions(&user, query.start_message_id, query.limit)
+        .await?;
+    Ok(Json(mentions))
+}
+
+// 把聊天室中的提及标记为已读
+pub(crate) async fn read_mentions(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<ChatIdPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .message_service
+        .read_mentions(&user, payload.chat_id)
+        .await?;
     Ok(StatusCode::OK)
 }
 
-pub(crate) async fn upload() -> Result<impl IntoResponse, AppError> {
-    Ok(())
+// 添加表情回应
+pub(crate) async fn add_reaction(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<ReactionPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .message_service
+        .react(&user, payload.message_id, payload.emoji, true)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+// 取消表情回应
+pub(crate) async fn remove_reaction(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<ReactionPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .message_service
+        .react(&user, payload.message_id, payload.emoji, false)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+// multipart 上传，字段 chat_id 需要在 file 之前
+pub(crate) async fn upload(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, AppError> {
+    let mut chat_id: Option<i64> = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::InvalidUpload(e.body_text()))?
+    {
+        match field.name() {
+            Some("chat_id") => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::InvalidUpload(e.body_text()))?;
+                chat_id = Some(
+                    text.trim()
+                        .parse()
+                        .map_err(|_| AppError::InvalidUpload("invalid chat_id".to_string()))?,
+                );
+            }
+            Some("file") => {
+                let chat_id = chat_id.ok_or_else(|| {
+                    AppError::InvalidUpload("chat_id must be sent before file".to_string())
+                })?;
+                let filename = field.file_name().unwrap_or_default().to_string();
+                let mime_type = field
+                    .content_type()
+                    .unwrap_or("application/octet-stream")
+                    .to_string();
+                let data = field
+                    .bytes()
+                    .await
+                    .map_err(|e| AppError::InvalidUpload(e.body_text()))?;
+                let uploaded = state
+                    .message_service
+                    .upload(&user, chat_id, filename, mime_type, data)
+                    .await?;
+                return Ok(Json(uploaded));
+            }
+            _ => {}
+        }
+    }
+    Err(AppError::InvalidUpload("missing file".to_string()))
+}
+
+// 申请预签名直传地址
+pub(crate) async fn presign_upload(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<PresignUpload>,
+) -> Result<impl IntoResponse, AppError> {
+    let presigned = state
+        .message_service
+        .presign_upload(
+            &user,
+            payload.chat_id,
+            payload.filename,
+            payload.mime_type,
+            payload.size,
+        )
+        .await?;
+    Ok(Json(presigned))
+}
+
+// 确认直传完成
+pub(crate) async fn confirm_upload(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<ConfirmUpload>,
+) -> Result<impl IntoResponse, AppError> {
+    let uploaded = state
+        .message_service
+        .confirm_upload(&user, payload.upload_id)
+        .await?;
+    Ok(Json(uploaded))
+}
+
+// 创建分片上传会话，参数和预签名直传相同
+pub(crate) async fn create_upload_session(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<PresignUpload>,
+) -> Result<impl IntoResponse, AppError> {
+    let session = state
+        .upload_session_service
+        .create(
+            &user,
+            payload.chat_id,
+            payload.filename,
+            payload.mime_type,
+            payload.size,
+        )
+        .await?;
+    Ok(Json(session))
+}
+
+// 上传一个分片，请求体是分片的原始内容
+pub(crate) async fn put_chunk(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Path((session_id, index)): Path<(String, i32)>,
+    data: Bytes,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .upload_session_service
+        .put_chunk(&user, &session_id, index, data)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// 查询分片上传会话的状态
+pub(crate) async fn upload_session_status(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<UploadSessionQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let status = state
+        .upload_session_service
+        .status(&user, &payload.session_id)
+        .await?;
+    Ok(Json(status))
+}
+
+// 合并分片，完成上传
+pub(crate) async fn finalize_upload_session(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<UploadSessionQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let uploaded = state
+        .upload_session_service
+        .finalize(&user, &payload.session_id)
+        .await?;
+    Ok(Json(uploaded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_query_cursor() {
+        let query = |before, after, around| MessageQuery {
+            chat_id: 1,
+            before,
+            after,
+            around,
+            ..Default::default()
+        };
+        assert_eq!(
+            query(None, None, None).cursor().unwrap(),
+            HistoryCursor::Latest
+        );
+        assert_eq!(
+            query(Some(5), None, None).cursor().unwrap(),
+            HistoryCursor::Before(5)
+        );
+        assert_eq!(
+            query(None, Some(5), None).cursor().unwrap(),
+            HistoryCursor::After(5)
+        );
+        assert_eq!(
+            query(None, None, Some(5)).cursor().unwrap(),
+            HistoryCursor::Around(5)
+        );
+        assert!(query(Some(5), Some(6), None).cursor().is_err());
+        assert!(query(Some(0), None, None).cursor().is_err());
+        assert!(query(None, Some(i64::MIN), None).cursor().is_err());
+        assert!(query(None, None, Some(-1)).cursor().is_err());
+    }
 }
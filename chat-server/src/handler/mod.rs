@@ -1,5 +1,6 @@
 pub(crate) mod auth;
 pub(crate) mod chat;
+pub(crate) mod file;
 pub(crate) mod message;
 pub(crate) mod user;
 pub(crate) mod workspace;
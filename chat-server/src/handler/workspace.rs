@@ -1,7 +1,8 @@
-use axum::{extract::State, response::IntoResponse, Json};
+use axum::{extract::State, response::IntoResponse, Extension, Json};
+use chat_core::models::user::CurUser;
 use serde::Deserialize;
 
-use crate::{error::AppError, AppState};
+use crate::{error::AppError, models::role::WorkspaceRole, AppState};
 
 #[derive(Debug, Clone, Deserialize)]
 pub(crate) struct WorkspaceCreate {
@@ -16,6 +17,17 @@ pub(crate) struct WorkspaceChangeOwner {
     pub owner_id: i64,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct WorkspaceSetRole {
+    pub user_id: i64,
+    pub role: WorkspaceRole,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct WorkspaceSetRetention {
+    pub retention_days: Option<u32>,
+}
+
 pub(crate) async fn create(
     State(state): State<AppState>,
     Json(workspace_create): Json<WorkspaceCreate>,
@@ -31,15 +43,50 @@ pub(crate) async fn create(
     Ok(())
 }
 pub(crate) async fn change_owner(
+    Extension(user): Extension<CurUser>,
     State(state): State<AppState>,
     Json(workspace_change_owner): Json<WorkspaceChangeOwner>,
 ) -> Result<impl IntoResponse, AppError> {
     state
         .workspace_service
         .change_owner(
+            &user,
             workspace_change_owner.workspace_id,
             workspace_change_owner.owner_id,
         )
         .await?;
     Ok(())
 }
+
+pub(crate) async fn set_role(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(workspace_set_role): Json<WorkspaceSetRole>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .workspace_service
+        .set_role(&user, workspace_set_role.user_id, workspace_set_role.role)
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn set_retention(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+    Json(payload): Json<WorkspaceSetRetention>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .workspace_service
+        .set_retention(&user, payload.retention_days)
+        .await?;
+    Ok(())
+}
+
+// 查询当前空间的存储用量和配额
+pub(crate) async fn storage_usage(
+    Extension(user): Extension<CurUser>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let usage = state.storage_service.usage(user.ws_id).await?;
+    Ok(Json(usage))
+}
@@ -1,22 +1,27 @@
 use crate::{
     mq::producers::outbox_message_producer::OutboxMessageProducer,
     services::{
-        chat_service::ChatService, message_publish_service::MessagePublishService,
-        message_service::MessageService, outbox_message_service::OutboxMessageService,
+        auth_service::AuthService, authz_service::AuthzService, chat_service::ChatService,
+        message_publish_service::MessagePublishService, message_service::MessageService,
+        outbox_message_service::OutboxMessageService, storage_service::StorageService,
+        thumbnail_service::ThumbnailService, upload_session_service::UploadSessionService,
         user_service::UserService, workspace_service::WorkspaceService,
     },
 };
 
 use anyhow::Result;
 use chat_core::{
-    models::user::CurUser,
-    utils::{jwt::DecodingKey, redis_lock::RedisLock},
+    utils::{
+        jwt::{DecodingKey, VerifiedToken},
+        redis_lock::RedisLock,
+        revoked_token::RevokedTokens,
+    },
     TokenVerify,
 };
 use sqlx::{MySql, MySqlPool, Pool};
 use std::{ops::Deref, sync::Arc, time::Duration};
 use tokio_cron_scheduler::{Job, JobScheduler};
-use tracing::info;
+use tracing::{info, warn};
 
 mod config;
 mod error;
@@ -27,6 +32,7 @@ mod mq;
 mod router;
 mod schedules;
 mod services;
+mod storage;
 
 pub use config::*;
 pub use router::*;
@@ -45,8 +51,12 @@ impl AppState {
 }
 
 impl TokenVerify for AppState {
-    fn verify_token(&self, token: &str) -> Result<CurUser, jwt_simple::Error> {
-        DecodingKey::load(&self.app_config.auth.public_key)?.verify(token)
+    fn verify_token(&self, token: &str) -> Result<VerifiedToken, jwt_simple::Error> {
+        self.decoding_key.decode(token)
+    }
+
+    async fn is_token_revoked(&self, jti: &str) -> Result<bool> {
+        self.revoked_tokens.is_revoked(jti).await
     }
 }
 
@@ -54,14 +64,19 @@ impl TokenVerify for AppState {
 pub struct AppStateInner {
     pub(crate) app_config: AppConfig,
     pub(crate) user_service: Arc<UserService>,
+    pub(crate) auth_service: Arc<AuthService>,
     pub(crate) chat_service: Arc<ChatService>,
     pub(crate) message_service: Arc<MessageService>,
+    pub(crate) upload_session_service: Arc<UploadSessionService>,
+    pub(crate) storage_service: Arc<StorageService>,
     pub(crate) workspace_service: Arc<WorkspaceService>,
     pub(crate) outbox_message_service: Arc<OutboxMessageService>,
     pub(crate) message_publish_service: Arc<MessagePublishService>,
     #[allow(unused)]
     pub(crate) db_pool: Pool<MySql>,
     pub(crate) redis_client: redis::Client,
+    pub(crate) revoked_tokens: RevokedTokens,
+    pub(crate) decoding_key: DecodingKey,
 }
 
 impl AppStateInner {
@@ -74,18 +89,58 @@ impl AppStateInner {
             redis::Client::open(&*app_config.redis.url).expect("can't connect to redis");
 
         let user_service = Arc::new(UserService::new(db_pool.clone()));
-        let chat_service = Arc::new(ChatService::new(db_pool.clone(), Arc::clone(&user_service)));
+        // 签名密钥和公钥只在启动时解析一次
+        let encoding_key = app_config.auth.encoding_key()?;
+        let decoding_key = app_config.auth.decoding_key()?;
+        let auth_service = Arc::new(AuthService::new(
+            db_pool.clone(),
+            Arc::clone(&user_service),
+            encoding_key,
+            app_config.auth.refresh_token_ttl,
+        ));
+        let authz_service = Arc::new(AuthzService::new(db_pool.clone()));
+        let chat_service = Arc::new(ChatService::new(
+            db_pool.clone(),
+            Arc::clone(&user_service),
+            Arc::clone(&authz_service),
+        ));
+        let storage = storage::from_config(&app_config.storage)?;
+        let thumbnail_service = Arc::new(ThumbnailService::new(
+            db_pool.clone(),
+            Arc::clone(&storage),
+            app_config.storage.thumbnail_sizes.clone(),
+        ));
+        let storage_service = Arc::new(StorageService::new(
+            db_pool.clone(),
+            Arc::clone(&storage),
+            app_config.storage.default_quota,
+            app_config.storage.default_retention_days,
+        ));
         let message_service = Arc::new(MessageService::new(
             db_pool.clone(),
             Arc::clone(&user_service),
             Arc::clone(&chat_service),
+            thumbnail_service,
+            Arc::clone(&storage_service),
+            storage,
+            app_config.storage.clone(),
+            app_config.message.clone(),
+            app_config.server.file_base_url.clone(),
+        ));
+        let upload_session_service = Arc::new(UploadSessionService::new(
+            db_pool.clone(),
+            Arc::clone(&message_service),
+            app_config.storage.chunked.clone(),
         ));
         let workspace_service = Arc::new(WorkspaceService::new(
             db_pool.clone(),
             Arc::clone(&user_service),
+            Arc::clone(&authz_service),
         ));
         let outbox_message_service = Arc::new(OutboxMessageService::new(db_pool.clone()));
 
+        let revoked_tokens = RevokedTokens::new(redis_client.clone());
+
         let outbox_message_producer = Arc::new(OutboxMessageProducer::new(redis_client.clone()));
         let message_publish_service = Arc::new(MessagePublishService::new(
             Arc::clone(&outbox_message_producer),
@@ -94,13 +149,18 @@ impl AppStateInner {
         Ok(Self {
             app_config,
             user_service,
+            auth_service,
             chat_service,
             message_service,
+            upload_session_service,
+            storage_service,
             workspace_service,
             outbox_message_service,
             message_publish_service,
             db_pool,
             redis_client,
+            revoked_tokens,
+            decoding_key,
         })
     }
 }
@@ -117,6 +177,43 @@ pub async fn start_background_scheduler(state: AppState) -> Result<()> {
     tokio::spawn(async move {
         let sched = JobScheduler::new().await.expect("can't create scheduler");
 
+        // 每5分钟清理一次过期的分片上传会话和没有确认的直传文件
+        let cleanup_state = state.clone();
+        sched
+            .add(
+                Job::new_async("0 0/5 * * * *", move |_uuid, mut _l| {
+                    let state_cloned = cleanup_state.clone();
+                    Box::pin(async move {
+                        if let Err(e) = cleanup_upload_sessions(state_cloned.clone()).await {
+                            warn!("cleanup upload sessions failed: {:?}", e);
+                        }
+                        if let Err(e) = cleanup_pending_uploads(state_cloned).await {
+                            warn!("cleanup pending uploads failed: {:?}", e);
+                        }
+                    })
+                })
+                .expect("can't create job"),
+            )
+            .await
+            .expect("can't add job");
+
+        // 每小时清理一次超过保留期限的附件
+        let retention_state = state.clone();
+        sched
+            .add(
+                Job::new_async("0 0 * * * *", move |_uuid, mut _l| {
+                    let state_cloned = retention_state.clone();
+                    Box::pin(async move {
+                        if let Err(e) = cleanup_expired_attachments(state_cloned).await {
+                            warn!("cleanup expired attachments failed: {:?}", e);
+                        }
+                    })
+                })
+                .expect("can't create job"),
+            )
+            .await
+            .expect("can't add job");
+
         // Add async job
         sched
             .add(
@@ -171,3 +268,37 @@ async fn send_messages(state: AppState) -> Result<()> {
 
     Ok(())
 }
+
+async fn cleanup_upload_sessions(state: AppState) -> Result<()> {
+    // 多个实例同时运行时只需要一个实例清理
+    let mut conn = state.redis_client.get_connection()?;
+    let mut lock = RedisLock::new(&mut conn, "lock:upload_session_cleanup");
+    if let Err(e) = lock.acquire(Some(Duration::from_secs(60))) {
+        info!("can't get lock: {}", e);
+        return Ok(());
+    }
+    state.upload_session_service.cleanup_expired().await?;
+    Ok(())
+}
+
+async fn cleanup_pending_uploads(state: AppState) -> Result<()> {
+    let mut conn = state.redis_client.get_connection()?;
+    let mut lock = RedisLock::new(&mut conn, "lock:pending_upload_cleanup");
+    if let Err(e) = lock.acquire(Some(Duration::from_secs(60))) {
+        info!("can't get lock: {}", e);
+        return Ok(());
+    }
+    state.storage_service.cleanup_pending_uploads().await?;
+    Ok(())
+}
+
+async fn cleanup_expired_attachments(state: AppState) -> Result<()> {
+    let mut conn = state.redis_client.get_connection()?;
+    let mut lock = RedisLock::new(&mut conn, "lock:attachment_retention");
+    if let Err(e) = lock.acquire(Some(Duration::from_secs(600))) {
+        info!("can't get lock: {}", e);
+        return Ok(());
+    }
+    state.storage_service.cleanup_expired_attachments().await?;
+    Ok(())
+}
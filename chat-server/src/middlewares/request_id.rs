@@ -2,6 +2,16 @@ use super::REQUEST_ID_HEADER;
 use axum::{extract::Request, http::HeaderValue, middleware::Next, response::Response};
 use tracing::warn;
 
+tokio::task_local! {
+    // 当前请求的ID，错误响应中需要带上
+    pub(crate) static REQUEST_ID: String;
+}
+
+// 获取当前请求的ID，不在请求上下文中时返回None
+pub(crate) fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
 pub async fn set_request_id(mut req: Request, next: Next) -> Response {
     // if x-request-id exists, do nothing, otherwise generate a new one
 
@@ -22,7 +32,14 @@ pub async fn set_request_id(mut req: Request, next: Next) -> Response {
         }
     };
 
-    let mut res = next.run(req).await;
+    let mut res = match id.as_ref().and_then(|v| v.to_str().ok()) {
+        Some(request_id) => {
+            REQUEST_ID
+                .scope(request_id.to_string(), next.run(req))
+                .await
+        }
+        None => next.run(req).await,
+    };
 
     let Some(id) = id else {
         return res;
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chat_core::event;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, MySql, QueryBuilder};
+
+use crate::{models::upload::Upload, storage};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub(crate) struct Attachment {
+    pub id: i64,
+    pub message_id: i64,
+    pub chat_id: i64,
+    pub storage_key: String,
+    pub filename: String,
+    pub size: i64,
+    pub mime_type: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 可以下载的文件，附件或者附件的缩略图
+#[derive(Debug, Clone)]
+pub(crate) struct StoredFile {
+    pub storage_key: String,
+    pub filename: String,
+    pub size: i64,
+    pub mime_type: String,
+}
+
+impl Attachment {
+    // 批量保存消息的附件，附件信息来自上传记录
+    pub(crate) async fn create_batch<'a, E>(
+        message_id: i64,
+        chat_id: i64,
+        uploads: &[Upload],
+        executor: E,
+    ) -> Result<()>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        if uploads.is_empty() {
+            return Ok(());
+        }
+        let mut query_builder = QueryBuilder::new(
+            "INSERT INTO attachments (message_id, chat_id, storage_key, filename, size, mime_type, width, height) ",
+        );
+        query_builder.push_values(uploads, |mut b, upload| {
+            b.push_bind(message_id)
+                .push_bind(chat_id)
+                .push_bind(&upload.storage_key)
+                .push_bind(&upload.filename)
+                .push_bind(upload.size)
+                .push_bind(&upload.mime_type)
+                .push_bind(upload.width)
+                .push_bind(upload.height);
+        });
+        query_builder.build().execute(executor).await?;
+        Ok(())
+    }
+
+    // 查询多条消息的附件，按消息ID分组
+    pub(crate) async fn list_by_message_ids<'a, E>(
+        message_ids: &[i64],
+        executor: E,
+    ) -> Result<HashMap<i64, Vec<Attachment>>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let mut map: HashMap<i64, Vec<Attachment>> = HashMap::new();
+        if message_ids.is_empty() {
+            return Ok(map);
+        }
+        let mut query_builder = QueryBuilder::new("SELECT * FROM attachments WHERE message_id IN ");
+        query_builder.push_tuples(message_ids, |mut b, id| {
+            b.push_bind(id);
+        });
+        query_builder.push(" ORDER BY id");
+        let attachments: Vec<Attachment> =
+            query_builder.build_query_as().fetch_all(executor).await?;
+        for attachment in attachments {
+            map.entry(attachment.message_id)
+                .or_default()
+                .push(attachment);
+        }
+        Ok(map)
+    }
+
+    // 查询用户有权访问的附件：用户所在的聊天室中有消息引用了该文件
+    pub(crate) async fn find_accessible<'a, E>(
+        storage_key: &str,
+        user_id: i64,
+        executor: E,
+    ) -> Result<Option<Attachment>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query_as::<_, Attachment>(
+            r#"
+            SELECT a.* FROM attachments a
+            JOIN chat_members cm ON cm.chat_id = a.chat_id AND cm.user_id = ?
+            WHERE a.storage_key = ?
+            ORDER BY a.id LIMIT 1
+            "#,
+        )
+        .bind(user_id)
+        .bind(storage_key)
+        .fetch_optional(executor)
+        .await?;
+        Ok(res)
+    }
+
+    // 删除聊天室中引用该文件的附件，返回受影响的消息ID
+    pub(crate) async fn delete_in_chat(
+        chat_id: i64,
+        storage_key: &str,
+        tx: &mut sqlx::Transaction<'_, MySql>,
+    ) -> Result<Vec<i64>> {
+        let rows: Vec<(i64,)> = sqlx::query_as(
+            "SELECT DISTINCT message_id FROM attachments WHERE chat_id = ? AND storage_key = ?",
+        )
+        .bind(chat_id)
+        .bind(storage_key)
+        .fetch_all(&mut **tx)
+        .await?;
+        sqlx::query("DELETE FROM attachments WHERE chat_id = ? AND storage_key = ?")
+            .bind(chat_id)
+            .bind(storage_key)
+            .execute(&mut **tx)
+            .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    // 删除消息的所有附件
+    pub(crate) async fn delete_by_message_id<'a, E>(message_id: i64, executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query("DELETE FROM attachments WHERE message_id = ?")
+            .bind(message_id)
+            .execute(executor)
+            .await?;
+        Ok(res.rows_affected())
+    }
+}
+
+impl Attachment {
+    // 转换为事件中的附件，base_url 为文件下载地址前缀
+    pub(crate) fn to_event(&self, base_url: &str) -> event::Attachment {
+        event::Attachment {
+            url: storage::file_url(base_url, &self.storage_key),
+            filename: self.filename.clone(),
+            size: self.size,
+            mime_type: self.mime_type.clone(),
+            width: self.width,
+            height: self.height,
+            thumbnails: vec![],
+        }
+    }
+}
+
+impl Upload {
+    // 转换为事件中的附件，base_url 为文件下载地址前缀
+    pub(crate) fn to_attachment(&self, base_url: &str) -> event::Attachment {
+        event::Attachment {
+            url: storage::file_url(base_url, &self.storage_key),
+            filename: self.filename.clone(),
+            size: self.size,
+            mime_type: self.mime_type.clone(),
+            width: self.width,
+            height: self.height,
+            thumbnails: vec![],
+        }
+    }
+}
+
+impl From<Attachment> for StoredFile {
+    fn from(value: Attachment) -> Self {
+        Self {
+            storage_key: value.storage_key,
+            filename: value.filename,
+            size: value.size,
+            mime_type: value.mime_type,
+        }
+    }
+}
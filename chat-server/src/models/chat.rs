@@ -1,21 +1,89 @@
 use anyhow::{Ok, Result};
 use chat_core::chat_type::ChatType;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use sqlx::{
     types::chrono::{DateTime, Utc},
-    Executor, FromRow, MySql, QueryBuilder,
+    Executor, FromRow, MySql, Pool, QueryBuilder,
 };
 
+use crate::models::message_pin::MessagePin;
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub(crate) struct Chat {
+    pub id: i64,
+    pub ws_id: i64,
+    pub title: String,
+    pub r#type: ChatType,
+    // 最后一条消息，话题回复不算
+    pub last_message_id: Option<i64>,
+    pub last_activity_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// 聊天列表中的一个聊天，带上最后一条消息的预览、未读数和成员数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ChatSummary {
     pub id: i64,
     pub title: String,
     pub r#type: ChatType,
+    pub last_activity_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub member_count: i64,
+    pub unread_count: i64,
+    pub last_read_message_id: i64,
+    pub last_message: Option<MessagePreview>,
+}
+
+// 最后一条消息的预览，content 只保留开头的一段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct MessagePreview {
+    pub id: i64,
+    pub sender_id: i64,
+    pub sender_name: String,
+    pub content: String,
+    pub deleted: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+// 聊天列表的一页，next_cursor 为空表示没有更多了
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ChatPage {
+    pub chats: Vec<ChatSummary>,
+    pub next_cursor: Option<String>,
+}
+
+// 聊天列表的分页位置，按最后活跃时间和ID倒序
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ChatCursor {
+    pub last_activity_at: DateTime<Utc>,
+    pub id: i64,
 }
 
+#[derive(Debug, Clone, FromRow)]
+struct ChatSummaryRow {
+    id: i64,
+    title: String,
+    r#type: ChatType,
+    last_activity_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    member_count: i64,
+    last_read_message_id: i64,
+    last_message_id: Option<i64>,
+    last_sender_id: Option<i64>,
+    last_sender_name: Option<String>,
+    last_content: Option<String>,
+    last_deleted: Option<bool>,
+    last_created_at: Option<DateTime<Utc>>,
+}
+
+// 预览内容的最大字符数
+const PREVIEW_LEN: usize = 100;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct ChatDetails {
     pub id: i64,
@@ -24,16 +92,24 @@ pub(crate) struct ChatDetails {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub members: Vec<i64>,
+    // 置顶的消息，最近置顶的在前
+    pub pins: Vec<MessagePin>,
 }
 
 impl Chat {
-    // 根据聊天ID查询一个聊天
-    pub(crate) async fn find_by_id<'a, E>(id: i64, lock: bool, executor: E) -> Result<Option<Chat>>
+    // 根据聊天ID查询一个聊天，只能查到所在空间的聊天
+    pub(crate) async fn find_by_id<'a, E>(
+        id: i64,
+        ws_id: i64,
+        lock: bool,
+        executor: E,
+    ) -> Result<Option<Chat>>
     where
         E: Executor<'a, Database = MySql>,
     {
         let mut query_builder = QueryBuilder::new("SELECT * FROM chats WHERE id = ");
         query_builder.push_bind(id);
+        query_builder.push(" AND ws_id = ").push_bind(ws_id);
         if lock {
             query_builder.push(" FOR UPDATE");
         }
@@ -44,11 +120,17 @@ impl Chat {
             .await?)
     }
     // 创建聊天
-    pub(crate) async fn create<'a, E>(title: String, r#type: ChatType, executor: E) -> Result<i64>
+    pub(crate) async fn create<'a, E>(
+        ws_id: i64,
+        title: String,
+        r#type: ChatType,
+        executor: E,
+    ) -> Result<i64>
     where
         E: Executor<'a, Database = MySql>,
     {
-        let res = sqlx::query("INSERT INTO chats (title, type) VALUES (?, ?)")
+        let res = sqlx::query("INSERT INTO chats (ws_id, title, type) VALUES (?, ?, ?)")
+            .bind(ws_id)
             .bind(title)
             .bind(r#type)
             .execute(executor)
@@ -66,16 +148,215 @@ impl Chat {
             .await?;
         Ok(res.last_insert_id() as i64)
     }
-    // 列出用户所有聊天
-    pub(crate) async fn list_by_user_id<'a, E>(user_id: i64, executor: E) -> Result<Vec<Chat>>
+    // 修改聊天标题
+    pub(crate) async fn rename<'a, E>(id: i64, title: String, executor: E) -> Result<u64>
+    where
+        E: Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query("UPDATE chats SET title = ?, updated_at = ? WHERE id = ?")
+            .bind(title)
+            .bind(Utc::now())
+            .bind(id)
+            .execute(executor)
+            .await?;
+        Ok(res.rows_affected())
+    }
+    // 更新最后一条消息和最后活跃时间
+    pub(crate) async fn touch_activity<'a, E>(id: i64, message_id: i64, executor: E) -> Result<u64>
+    where
+        E: Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            "UPDATE chats SET last_message_id = ?, last_activity_at = NOW() WHERE id = ?",
+        )
+        .bind(message_id)
+        .bind(id)
+        .execute(executor)
+        .await?;
+        Ok(res.rows_affected())
+    }
+
+    // 加入或离开成员后更新聊天室的成员数
+    pub(crate) async fn add_member_count<'a, E>(id: i64, delta: i64, executor: E) -> Result<u64>
     where
         E: Executor<'a, Database = MySql>,
     {
+        let res = sqlx::query("UPDATE chats SET member_count = member_count + ? WHERE id = ?")
+            .bind(delta)
+            .bind(id)
+            .execute(executor)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    // 分页列出用户在空间中的聊天，按最后活跃时间倒序
+    pub(crate) async fn list_by_user_id(
+        user_id: i64,
+        ws_id: i64,
+        cursor: Option<ChatCursor>,
+        limit: i64,
+        pool: &Pool<MySql>,
+    ) -> Result<Vec<ChatSummary>> {
+        let mut query_builder = QueryBuilder::new(
+            r#"
+            SELECT chats.id, chats.title, chats.type, chats.last_activity_at,
+                chats.created_at, chats.updated_at, chats.member_count,
+                chat_members.last_read_message_id,
+                lm.id AS last_message_id,
+                lm.sender_id AS last_sender_id,
+                u.fullname AS last_sender_name,
+                lm.content AS last_content,
+                lm.deleted_at IS NOT NULL AS last_deleted,
+                lm.created_at AS last_created_at
+            FROM chat_members
+            INNER JOIN chats ON chats.id = chat_members.chat_id
+            LEFT JOIN messages lm ON lm.id = chats.last_message_id
+            LEFT JOIN users u ON u.id = lm.sender_id
+            WHERE chat_members.user_id = "#,
+        );
+        query_builder.push_bind(user_id);
+        query_builder.push(" AND chats.ws_id = ").push_bind(ws_id);
+        if let Some(cursor) = cursor {
+            query_builder
+                .push(" AND (chats.last_activity_at < ")
+                .push_bind(cursor.last_activity_at)
+                .push(" OR (chats.last_activity_at = ")
+                .push_bind(cursor.last_activity_at)
+                .push(" AND chats.id < ")
+                .push_bind(cursor.id)
+                .push("))");
+        }
+        query_builder
+            .push(" ORDER BY chats.last_activity_at DESC, chats.id DESC LIMIT ")
+            .push_bind(limit);
+
+        let rows: Vec<ChatSummaryRow> = query_builder.build_query_as().fetch_all(pool).await?;
+        let chat_ids = rows.iter().map(|row| row.id).collect::<Vec<_>>();
+        let unread_counts = Self::unread_counts(user_id, &chat_ids, pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let mut chat = ChatSummary::from(row);
+                chat.unread_count = unread_counts.get(&chat.id).copied().unwrap_or_default();
+                chat
+            })
+            .collect())
+    }
+
+    // 只统计当前页聊天的未读数
+    // 未读数不包括自己发送的消息、话题回复和已删除的消息
+    async fn unread_counts(
+        user_id: i64,
+        chat_ids: &[i64],
+        pool: &Pool<MySql>,
+    ) -> Result<HashMap<i64, i64>> {
+        if chat_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
         let mut query_builder = QueryBuilder::new(
-            "SELECT DISTINCT chats.* FROM chats INNER JOIN chat_members ON chats.id = chat_members.chat_id WHERE chat_members.user_id = ");
+            r#"
+            SELECT m.chat_id, COUNT(*) AS unread_count
+            FROM chat_members cm
+            INNER JOIN messages m ON m.chat_id = cm.chat_id
+                AND m.parent_message_id IS NULL
+                AND m.id > cm.last_read_message_id
+            WHERE m.sender_id <> cm.user_id AND m.deleted_at IS NULL AND cm.user_id = "#,
+        );
         query_builder.push_bind(user_id);
+        query_builder.push(" AND cm.chat_id IN (");
+        let mut separated = query_builder.separated(',');
+        for chat_id in chat_ids {
+            separated.push_bind(*chat_id);
+        }
+        separated.push_unseparated(") GROUP BY m.chat_id");
+
+        let rows: Vec<(i64, i64)> = query_builder.build_query_as().fetch_all(pool).await?;
+        Ok(rows.into_iter().collect())
+    }
+}
+
+impl ChatSummary {
+    pub(crate) fn cursor(&self) -> ChatCursor {
+        ChatCursor {
+            last_activity_at: self.last_activity_at,
+            id: self.id,
+        }
+    }
+}
+
+impl ChatCursor {
+    // 编码成 “秒级时间戳_聊天ID” 返回给客户端
+    pub(crate) fn encode(&self) -> String {
+        format!("{}_{}", self.last_activity_at.timestamp(), self.id)
+    }
+
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let (ts, id) = value.split_once('_')?;
+        Some(Self {
+            last_activity_at: DateTime::from_timestamp(ts.parse().ok()?, 0)?,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+impl From<ChatSummaryRow> for ChatSummary {
+    fn from(row: ChatSummaryRow) -> Self {
+        let last_message = match (row.last_message_id, row.last_created_at) {
+            (Some(id), Some(created_at)) => Some(MessagePreview {
+                id,
+                sender_id: row.last_sender_id.unwrap_or_default(),
+                sender_name: row.last_sender_name.unwrap_or_default(),
+                content: preview(&row.last_content.unwrap_or_default()),
+                deleted: row.last_deleted.unwrap_or_default(),
+                created_at,
+            }),
+            _ => None,
+        };
+        Self {
+            id: row.id,
+            title: row.title,
+            r#type: row.r#type,
+            last_activity_at: row.last_activity_at,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            member_count: row.member_count,
+            unread_count: 0,
+            last_read_message_id: row.last_read_message_id,
+            last_message,
+        }
+    }
+}
+
+// 截取消息开头的一段作为预览，换行替换成空格
+fn preview(content: &str) -> String {
+    let content = content.trim().replace(['\r', '\n'], " ");
+    match content.char_indices().nth(PREVIEW_LEN) {
+        Some((idx, _)) => format!("{}…", &content[..idx]),
+        None => content,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_cursor() {
+        let cursor = ChatCursor {
+            last_activity_at: DateTime::from_timestamp(1_760_000_000, 0).unwrap(),
+            id: 42,
+        };
+        assert_eq!(cursor.encode(), "1760000000_42");
+        assert_eq!(ChatCursor::parse(&cursor.encode()), Some(cursor));
+        assert_eq!(ChatCursor::parse("abc"), None);
+        assert_eq!(ChatCursor::parse("1_x"), None);
+    }
 
-        let res: Vec<Chat> = query_builder.build_query_as().fetch_all(executor).await?;
-        Ok(res)
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview(" hello\nworld "), "hello world");
+        let long = "好".repeat(PREVIEW_LEN + 5);
+        assert_eq!(preview(&long), format!("{}…", "好".repeat(PREVIEW_LEN)));
+        assert_eq!(preview(&"好".repeat(PREVIEW_LEN)), "好".repeat(PREVIEW_LEN));
     }
 }
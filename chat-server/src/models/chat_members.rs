@@ -1,12 +1,15 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use sqlx::{prelude::FromRow, Executor, MySql, Pool, QueryBuilder};
+use sqlx::{prelude::FromRow, Executor, MySql, QueryBuilder};
+
+use crate::models::role::ChatRole;
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub(crate) struct ChatMembers {
     pub id: i64,
     pub chat_id: i64,
     pub user_id: i64,
+    pub role: ChatRole,
 }
 
 impl ChatMembers {
@@ -27,6 +30,7 @@ impl ChatMembers {
     pub(crate) async fn add_members<'a, E>(
         chat_id: i64,
         user_ids: Vec<i64>,
+        role: ChatRole,
         executor: E,
     ) -> Result<u64>
     where
@@ -36,9 +40,10 @@ impl ChatMembers {
             return Ok(0);
         }
 
-        let mut query_builder = QueryBuilder::new("INSERT INTO chat_members (chat_id, user_id) ");
+        let mut query_builder =
+            QueryBuilder::new("INSERT INTO chat_members (chat_id, user_id, role) ");
         query_builder.push_values(user_ids, |mut b, user_id| {
-            b.push_bind(chat_id).push_bind(user_id);
+            b.push_bind(chat_id).push_bind(user_id).push_bind(role);
         });
 
         let a = query_builder.build().execute(executor).await?;
@@ -47,11 +52,14 @@ impl ChatMembers {
     }
     // 删除聊天成员
     // 此处仅仅考虑移除操作，其他的逻辑在上层实现
-    pub(crate) async fn remove_members(
+    pub(crate) async fn remove_members<'a, E>(
         chat_id: i64,
         user_ids: Vec<i64>,
-        pool: &Pool<MySql>,
-    ) -> Result<u64> {
+        executor: E,
+    ) -> Result<u64>
+    where
+        E: Executor<'a, Database = MySql>,
+    {
         if user_ids.is_empty() {
             return Ok(0);
         }
@@ -66,10 +74,70 @@ impl ChatMembers {
         separated.push_unseparated(")");
 
         let sql = query_builder.build();
-        let res = sql.execute(pool).await?;
+        let res = sql.execute(executor).await?;
+        Ok(res.rows_affected())
+    }
+
+    // 查询用户在聊天室中的角色，不是成员时返回None
+    pub(crate) async fn find_role<'a, E>(
+        chat_id: i64,
+        user_id: i64,
+        lock: bool,
+        executor: E,
+    ) -> Result<Option<ChatRole>>
+    where
+        E: Executor<'a, Database = MySql>,
+    {
+        let mut query_builder = QueryBuilder::new("SELECT role FROM chat_members WHERE chat_id = ");
+        query_builder.push_bind(chat_id);
+        query_builder.push(" AND user_id = ").push_bind(user_id);
+        query_builder.push(" LIMIT 1");
+        if lock {
+            query_builder.push(" FOR UPDATE");
+        }
+        let res: Option<ChatRole> = query_builder
+            .build_query_scalar()
+            .fetch_optional(executor)
+            .await?;
+        Ok(res)
+    }
+
+    // 修改成员角色
+    pub(crate) async fn update_role<'a, E>(
+        chat_id: i64,
+        user_id: i64,
+        role: ChatRole,
+        executor: E,
+    ) -> Result<u64>
+    where
+        E: Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query("UPDATE chat_members SET role = ? WHERE chat_id = ? AND user_id = ?")
+            .bind(role)
+            .bind(chat_id)
+            .bind(user_id)
+            .execute(executor)
+            .await?;
         Ok(res.rows_affected())
     }
 
+    // 查询最早加入聊天室的成员，用于拥有者离开后转移拥有者
+    pub(crate) async fn find_earliest_member<'a, E>(
+        chat_id: i64,
+        executor: E,
+    ) -> Result<Option<i64>>
+    where
+        E: Executor<'a, Database = MySql>,
+    {
+        let res: Option<i64> = sqlx::query_scalar(
+            "SELECT user_id FROM chat_members WHERE chat_id = ? ORDER BY id LIMIT 1",
+        )
+        .bind(chat_id)
+        .fetch_optional(executor)
+        .await?;
+        Ok(res)
+    }
+
     //根据聊天室ID删除聊天室成员
     pub(crate) async fn delete_members_by_chat_id<'a, E>(chat_id: i64, executor: E) -> Result<u64>
     where
@@ -81,4 +149,51 @@ impl ChatMembers {
             .await?;
         Ok(res.rows_affected())
     }
+
+    // 查询成员的已读位置，不是成员时返回None
+    pub(crate) async fn find_last_read<'a, E>(
+        chat_id: i64,
+        user_id: i64,
+        lock: bool,
+        executor: E,
+    ) -> Result<Option<i64>>
+    where
+        E: Executor<'a, Database = MySql>,
+    {
+        let mut query_builder =
+            QueryBuilder::new("SELECT last_read_message_id FROM chat_members WHERE chat_id = ");
+        query_builder.push_bind(chat_id);
+        query_builder.push(" AND user_id = ").push_bind(user_id);
+        query_builder.push(" LIMIT 1");
+        if lock {
+            query_builder.push(" FOR UPDATE");
+        }
+        let res: Option<i64> = query_builder
+            .build_query_scalar()
+            .fetch_optional(executor)
+            .await?;
+        Ok(res)
+    }
+
+    // 更新成员的已读位置，已读位置只能前进
+    pub(crate) async fn update_last_read<'a, E>(
+        chat_id: i64,
+        user_id: i64,
+        message_id: i64,
+        executor: E,
+    ) -> Result<u64>
+    where
+        E: Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            "UPDATE chat_members SET last_read_message_id = ? WHERE chat_id = ? AND user_id = ? AND last_read_message_id < ?",
+        )
+        .bind(message_id)
+        .bind(chat_id)
+        .bind(user_id)
+        .bind(message_id)
+        .execute(executor)
+        .await?;
+        Ok(res.rows_affected())
+    }
 }
@@ -1,60 +1,429 @@
 use anyhow::Result;
+use chat_core::{
+    event::{Attachment, ThreadInfo},
+    message_kind::{MessageKind, MessagePayload},
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, MySql, Pool, QueryBuilder};
 
+use crate::models::{message_reaction::ReactionCount, message_search::MessageSearch};
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
 pub(crate) struct Message {
     pub id: i64,
     pub chat_id: i64,
     pub sender_id: i64,
+    // 客户端生成的消息ID，用于重试去重
+    pub client_msg_id: Option<String>,
+    // 话题回复的父消息
+    pub parent_message_id: Option<i64>,
+    // 话题的回复数和最后回复时间
+    pub reply_count: i32,
+    pub last_reply_at: Option<DateTime<Utc>>,
+    pub kind: MessageKind,
     pub content: String,
-    pub files: String,
+    // 卡片等结构化内容
+    pub payload: Option<MessagePayload>,
+    // 附件超过保留期限已经被清理
+    pub attachments_expired: bool,
+    // 最后编辑时间，没有编辑过为空
+    pub edited_at: Option<DateTime<Utc>>,
+    // 删除的消息保留记录，内容和附件清空
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<i64>,
     pub created_at: DateTime<Utc>,
+    // 附件保存在 attachments 表中，查询后单独填充
+    #[sqlx(skip)]
+    pub attachments: Vec<Attachment>,
+    // 表情回应的汇总，查询后单独填充
+    #[sqlx(skip)]
+    pub reactions: Vec<ReactionCount>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MessageCreate {
+    pub chat_id: i64,
+    pub sender_id: i64,
+    pub client_msg_id: Option<String>,
+    pub parent_message_id: Option<i64>,
+    pub kind: MessageKind,
+    pub content: String,
+    pub payload: Option<MessagePayload>,
+}
+
+// 客户端发送的消息
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NewMessage {
+    pub kind: MessageKind,
+    pub content: String,
+    pub payload: Option<MessagePayload>,
+    // 附件的地址
+    pub attachments: Vec<String>,
+    // 话题回复的父消息
+    pub parent_message_id: Option<i64>,
+    // 客户端生成的消息ID
+    pub client_msg_id: Option<String>,
+}
+
+// 发送消息的结果，duplicate 表示是重试的请求，返回的是第一次发送的消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SentMessage {
+    pub message_id: i64,
+    pub client_msg_id: Option<String>,
+    pub duplicate: bool,
+}
+
+// 消息列表的分页方式，都按消息ID计算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HistoryCursor {
+    // 最新的消息
+    Latest,
+    // 某条消息之前（不包含）
+    Before(i64),
+    // 某条消息之后（不包含）
+    After(i64),
+    // 某条消息前后，包含这条消息，用于跳转到消息
+    Around(i64),
+}
+
+// 消息列表的一页，messages 按ID正序
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct MessagePage {
+    pub messages: Vec<Message>,
+    // 是否还有更早的消息
+    pub has_more_before: bool,
+    // 是否还有更新的消息
+    pub has_more_after: bool,
 }
 
 impl Message {
     // 创建消息
-    pub(crate) async fn create<'a, E>(
+    pub(crate) async fn create<'a, E>(data: MessageCreate, executor: E) -> Result<i64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            r#"
+            INSERT INTO messages (chat_id, sender_id, client_msg_id, parent_message_id, kind, content, payload)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(data.chat_id)
+        .bind(data.sender_id)
+        .bind(data.client_msg_id)
+        .bind(data.parent_message_id)
+        .bind(data.kind)
+        .bind(data.content)
+        .bind(data.payload)
+        .execute(executor)
+        .await?;
+
+        Ok(res.last_insert_id() as i64)
+    }
+
+    // 根据客户端消息ID查询发送者已经发送的消息ID
+    pub(crate) async fn find_by_client_msg_id<'a, E>(
         chat_id: i64,
         sender_id: i64,
-        content: String,
-        files: String,
+        client_msg_id: &str,
         executor: E,
-    ) -> Result<i64>
+    ) -> Result<Option<i64>>
     where
         E: sqlx::Executor<'a, Database = MySql>,
     {
-        let res = sqlx::query(
-            "INSERT INTO messages (chat_id, sender_id, content, files) VALUES (?, ?, ?, ?)",
+        let res: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM messages WHERE chat_id = ? AND sender_id = ? AND client_msg_id = ?",
         )
         .bind(chat_id)
         .bind(sender_id)
-        .bind(content)
-        .bind(files)
+        .bind(client_msg_id)
+        .fetch_optional(executor)
+        .await?;
+        Ok(res)
+    }
+
+    // 父消息的回复数加一，更新最后回复时间，返回更新后的话题信息
+    pub(crate) async fn add_reply(
+        id: i64,
+        tx: &mut sqlx::Transaction<'_, MySql>,
+    ) -> Result<ThreadInfo> {
+        sqlx::query(
+            "UPDATE messages SET reply_count = reply_count + 1, last_reply_at = NOW() WHERE id = ?",
+        )
+        .bind(id)
+        .execute(&mut **tx)
+        .await?;
+        Self::thread_info(id, tx).await
+    }
+
+    // 删除回复后父消息的回复数减一，最后回复时间改为剩下的最新回复的时间
+    // 调用方需要先删除回复并锁住父消息
+    pub(crate) async fn remove_reply(
+        id: i64,
+        tx: &mut sqlx::Transaction<'_, MySql>,
+    ) -> Result<ThreadInfo> {
+        let (last_reply_at,): (Option<DateTime<Utc>>,) = sqlx::query_as(
+            "SELECT MAX(created_at) FROM messages WHERE parent_message_id = ? AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await?;
+        sqlx::query(
+            "UPDATE messages SET reply_count = GREATEST(reply_count - 1, 0), last_reply_at = ? WHERE id = ?",
+        )
+        .bind(last_reply_at)
+        .bind(id)
+        .execute(&mut **tx)
+        .await?;
+        Self::thread_info(id, tx).await
+    }
+
+    async fn thread_info(id: i64, tx: &mut sqlx::Transaction<'_, MySql>) -> Result<ThreadInfo> {
+        let (reply_count, last_reply_at): (i32, Option<DateTime<Utc>>) =
+            sqlx::query_as("SELECT reply_count, last_reply_at FROM messages WHERE id = ?")
+                .bind(id)
+                .fetch_one(&mut **tx)
+                .await?;
+        Ok(ThreadInfo::new(id, reply_count, last_reply_at))
+    }
+
+    // 根据ID查询
+    pub(crate) async fn find_by_id<'a, E>(
+        id: i64,
+        lock: bool,
+        executor: E,
+    ) -> Result<Option<Message>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let mut query_builder = QueryBuilder::new("SELECT * FROM messages WHERE id = ");
+        query_builder.push_bind(id);
+        if lock {
+            query_builder.push(" FOR UPDATE");
+        }
+        let res = query_builder
+            .build_query_as()
+            .fetch_optional(executor)
+            .await?;
+        Ok(res)
+    }
+
+    // 修改消息内容，返回编辑时间
+    pub(crate) async fn update_content(
+        id: i64,
+        content: &str,
+        tx: &mut sqlx::Transaction<'_, MySql>,
+    ) -> Result<DateTime<Utc>> {
+        sqlx::query("UPDATE messages SET content = ?, edited_at = NOW() WHERE id = ?")
+            .bind(content)
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        let (edited_at,): (DateTime<Utc>,) =
+            sqlx::query_as("SELECT edited_at FROM messages WHERE id = ?")
+                .bind(id)
+                .fetch_one(&mut **tx)
+                .await?;
+        Ok(edited_at)
+    }
+
+    // 删除消息，清空内容和结构化内容并记录删除者
+    pub(crate) async fn mark_deleted<'a, E>(id: i64, deleted_by: i64, executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            "UPDATE messages SET content = '', payload = NULL, deleted_at = NOW(), deleted_by = ? WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(deleted_by)
+        .bind(id)
         .execute(executor)
         .await?;
+        Ok(res.rows_affected())
+    }
 
-        Ok(res.last_insert_id() as i64)
+    // 查询 before 之前的消息，按ID倒序，before 为空时从最新的消息开始
+    // 话题回复不出现在聊天室的消息列表中
+    pub(crate) async fn list_before(
+        chat_id: i64,
+        before: Option<i64>,
+        limit: i64,
+        pool: &Pool<MySql>,
+    ) -> Result<Vec<Message>> {
+        let mut query_builder = QueryBuilder::new("SELECT * FROM messages WHERE chat_id = ");
+        query_builder.push_bind(chat_id);
+        query_builder.push(" AND parent_message_id IS NULL");
+        if let Some(before) = before {
+            query_builder.push(" AND id < ").push_bind(before);
+        }
+        query_builder
+            .push(" ORDER BY id DESC LIMIT ")
+            .push_bind(limit);
+
+        let res = query_builder.build_query_as().fetch_all(pool).await?;
+        Ok(res)
     }
 
-    // 获取最近消息
-    pub(crate) async fn recent(
+    // 查询 after 之后的消息，按ID正序
+    pub(crate) async fn list_after(
         chat_id: i64,
-        start_message_id: i64,
+        after: i64,
         limit: i64,
         pool: &Pool<MySql>,
     ) -> Result<Vec<Message>> {
         let mut query_builder = QueryBuilder::new("SELECT * FROM messages WHERE chat_id = ");
         query_builder.push_bind(chat_id);
-        if start_message_id > 0 {
-            query_builder.push(" AND id < ").push_bind(start_message_id);
+        query_builder.push(" AND parent_message_id IS NULL");
+        query_builder.push(" AND id > ").push_bind(after);
+        query_builder.push(" ORDER BY id LIMIT ").push_bind(limit);
+
+        let res = query_builder.build_query_as().fetch_all(pool).await?;
+        Ok(res)
+    }
+
+    // 全文检索用户在空间中所在聊天室的消息，按ID倒序，不包括已删除的消息
+    pub(crate) async fn search(
+        user_id: i64,
+        ws_id: i64,
+        search: &MessageSearch,
+        limit: i64,
+        pool: &Pool<MySql>,
+    ) -> Result<Vec<Message>> {
+        let mut query_builder = QueryBuilder::new(
+            r#"
+            SELECT m.* FROM messages m
+            INNER JOIN chat_members cm ON cm.chat_id = m.chat_id AND cm.user_id = "#,
+        );
+        query_builder.push_bind(user_id);
+        query_builder.push(" INNER JOIN chats c ON c.id = m.chat_id AND c.ws_id = ");
+        query_builder.push_bind(ws_id);
+        query_builder.push(" WHERE MATCH(m.content) AGAINST (");
+        query_builder.push_bind(search.boolean_query());
+        query_builder.push(" IN BOOLEAN MODE) AND m.deleted_at IS NULL");
+        if let Some(chat_id) = search.chat_id {
+            query_builder.push(" AND m.chat_id = ").push_bind(chat_id);
+        }
+        if let Some(sender_id) = search.sender_id {
+            query_builder
+                .push(" AND m.sender_id = ")
+                .push_bind(sender_id);
+        }
+        if let Some(from) = search.from {
+            query_builder.push(" AND m.created_at >= ").push_bind(from);
+        }
+        if let Some(to) = search.to {
+            query_builder.push(" AND m.created_at < ").push_bind(to);
+        }
+        match search.has_attachment {
+            Some(true) => {
+                query_builder
+                    .push(" AND EXISTS (SELECT 1 FROM attachments a WHERE a.message_id = m.id)");
+            }
+            Some(false) => {
+                query_builder.push(
+                    " AND NOT EXISTS (SELECT 1 FROM attachments a WHERE a.message_id = m.id)",
+                );
+            }
+            None => {}
+        }
+        if let Some(before) = search.before {
+            query_builder.push(" AND m.id < ").push_bind(before);
         }
         query_builder
-            .push(" ORDER BY created_at DESC LIMIT ")
+            .push(" ORDER BY m.id DESC LIMIT ")
             .push_bind(limit);
 
         let res = query_builder.build_query_as().fetch_all(pool).await?;
         Ok(res)
     }
+
+    // 是否有比 id 更早的消息
+    pub(crate) async fn has_before(chat_id: i64, id: i64, pool: &Pool<MySql>) -> Result<bool> {
+        let (exists,): (bool,) = sqlx::query_as(
+            "SELECT EXISTS(SELECT 1 FROM messages WHERE chat_id = ? AND parent_message_id IS NULL AND id < ?)",
+        )
+        .bind(chat_id)
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+        Ok(exists)
+    }
+
+    // 是否有比 id 更新的消息
+    pub(crate) async fn has_after(chat_id: i64, id: i64, pool: &Pool<MySql>) -> Result<bool> {
+        let (exists,): (bool,) = sqlx::query_as(
+            "SELECT EXISTS(SELECT 1 FROM messages WHERE chat_id = ? AND parent_message_id IS NULL AND id > ?)",
+        )
+        .bind(chat_id)
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+        Ok(exists)
+    }
+
+    // 查询聊天室中最新一条消息的ID，没有消息时返回0
+    pub(crate) async fn latest_id<'a, E>(chat_id: i64, executor: E) -> Result<i64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let (id,): (i64,) =
+            sqlx::query_as("SELECT COALESCE(MAX(id), 0) FROM messages WHERE chat_id = ?")
+                .bind(chat_id)
+                .fetch_one(executor)
+                .await?;
+        Ok(id)
+    }
+
+    // 话题中是否有比 id 更早的回复
+    pub(crate) async fn has_reply_before(
+        parent_message_id: i64,
+        id: i64,
+        pool: &Pool<MySql>,
+    ) -> Result<bool> {
+        let (exists,): (bool,) = sqlx::query_as(
+            "SELECT EXISTS(SELECT 1 FROM messages WHERE parent_message_id = ? AND id < ?)",
+        )
+        .bind(parent_message_id)
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+        Ok(exists)
+    }
+
+    // 分页查询话题的回复，按发送顺序，start_message_id 之后的回复
+    pub(crate) async fn replies(
+        parent_message_id: i64,
+        start_message_id: i64,
+        limit: i64,
+        pool: &Pool<MySql>,
+    ) -> Result<Vec<Message>> {
+        let mut query_builder =
+            QueryBuilder::new("SELECT * FROM messages WHERE parent_message_id = ");
+        query_builder.push_bind(parent_message_id);
+        if start_message_id > 0 {
+            query_builder.push(" AND id > ").push_bind(start_message_id);
+        }
+        query_builder.push(" ORDER BY id LIMIT ").push_bind(limit);
+
+        let res = query_builder.build_query_as().fetch_all(pool).await?;
+        Ok(res)
+    }
+
+    // 标记消息的附件已过期
+    pub(crate) async fn mark_attachments_expired<'a, E>(ids: &[i64], executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let mut query_builder =
+            QueryBuilder::new("UPDATE messages SET attachments_expired = TRUE WHERE id IN ");
+        query_builder.push_tuples(ids, |mut b, id| {
+            b.push_bind(id);
+        });
+        let res = query_builder.build().execute(executor).await?;
+        Ok(res.rows_affected())
+    }
 }
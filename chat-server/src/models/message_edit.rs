@@ -0,0 +1,52 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, MySql};
+
+// 消息的编辑历史，content 是编辑前的内容
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub(crate) struct MessageEdit {
+    pub id: i64,
+    pub message_id: i64,
+    pub editor_id: i64,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MessageEdit {
+    // 记录一次编辑
+    pub(crate) async fn create<'a, E>(
+        message_id: i64,
+        editor_id: i64,
+        content: &str,
+        executor: E,
+    ) -> Result<i64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            "INSERT INTO message_edits (message_id, editor_id, content) VALUES (?, ?, ?)",
+        )
+        .bind(message_id)
+        .bind(editor_id)
+        .bind(content)
+        .execute(executor)
+        .await?;
+        Ok(res.last_insert_id() as i64)
+    }
+
+    // 查询消息的编辑历史，按时间顺序
+    pub(crate) async fn list_by_message_id<'a, E>(
+        message_id: i64,
+        executor: E,
+    ) -> Result<Vec<MessageEdit>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query_as("SELECT * FROM message_edits WHERE message_id = ? ORDER BY id")
+            .bind(message_id)
+            .fetch_all(executor)
+            .await?;
+        Ok(res)
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, MySql, QueryBuilder};
+
+// 用户未读的提及，带上消息内容方便客户端展示
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub(crate) struct UnreadMention {
+    pub message_id: i64,
+    pub chat_id: i64,
+    pub sender_id: i64,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub(crate) struct MessageMention {
+    pub id: i64,
+    pub message_id: i64,
+    pub chat_id: i64,
+    pub user_id: i64,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MessageMention {
+    // 批量记录消息提及的用户
+    pub(crate) async fn create_batch<'a, E>(
+        message_id: i64,
+        chat_id: i64,
+        user_ids: &[i64],
+        executor: E,
+    ) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        if user_ids.is_empty() {
+            return Ok(0);
+        }
+        let mut query_builder = QueryBuilder::new(
+            "INSERT IGNORE INTO message_mentions (message_id, chat_id, user_id) ",
+        );
+        query_builder.push_values(user_ids, |mut b, user_id| {
+            b.push_bind(message_id)
+                .push_bind(chat_id)
+                .push_bind(user_id);
+        });
+        let res = query_builder.build().execute(executor).await?;
+        Ok(res.rows_affected())
+    }
+
+    // 查询用户在所有聊天室中未读的提及，已删除的消息和已退出的聊天室不展示
+    pub(crate) async fn list_unread<'a, E>(
+        user_id: i64,
+        start_message_id: i64,
+        limit: i64,
+        executor: E,
+    ) -> Result<Vec<UnreadMention>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let mut query_builder = QueryBuilder::new(
+            r#"
+            SELECT mm.message_id, mm.chat_id, m.sender_id, m.content, m.created_at
+            FROM message_mentions mm
+            JOIN messages m ON m.id = mm.message_id
+            JOIN chat_members cm ON cm.chat_id = mm.chat_id AND cm.user_id = mm.user_id
+            WHERE mm.read_at IS NULL AND m.deleted_at IS NULL AND mm.user_id = "#,
+        );
+        query_builder.push_bind(user_id);
+        if start_message_id > 0 {
+            query_builder
+                .push(" AND mm.message_id < ")
+                .push_bind(start_message_id);
+        }
+        query_builder
+            .push(" ORDER BY mm.message_id DESC LIMIT ")
+            .push_bind(limit);
+        let res = query_builder.build_query_as().fetch_all(executor).await?;
+        Ok(res)
+    }
+
+    // 把用户在聊天室中 message_id 及之前的提及标记为已读
+    pub(crate) async fn mark_read<'a, E>(
+        chat_id: i64,
+        user_id: i64,
+        message_id: i64,
+        executor: E,
+    ) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            "UPDATE message_mentions SET read_at = NOW() WHERE chat_id = ? AND user_id = ? AND message_id <= ? AND read_at IS NULL",
+        )
+        .bind(chat_id)
+        .bind(user_id)
+        .bind(message_id)
+        .execute(executor)
+        .await?;
+        Ok(res.rows_affected())
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, MySql};
+
+use crate::models::message::Message;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub(crate) struct MessagePin {
+    pub chat_id: i64,
+    pub message_id: i64,
+    pub pinned_by: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+// 置顶的消息和置顶信息
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub(crate) struct PinnedMessage {
+    #[sqlx(flatten)]
+    pub message: Message,
+    pub pinned_by: i64,
+    pub pinned_at: DateTime<Utc>,
+}
+
+impl MessagePin {
+    // 置顶消息，已经置顶过时返回0
+    pub(crate) async fn create<'a, E>(
+        chat_id: i64,
+        message_id: i64,
+        pinned_by: i64,
+        executor: E,
+    ) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            "INSERT IGNORE INTO message_pins (chat_id, message_id, pinned_by) VALUES (?, ?, ?)",
+        )
+        .bind(chat_id)
+        .bind(message_id)
+        .bind(pinned_by)
+        .execute(executor)
+        .await?;
+        Ok(res.rows_affected())
+    }
+
+    // 取消置顶，没有置顶过时返回0
+    pub(crate) async fn remove<'a, E>(chat_id: i64, message_id: i64, executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query("DELETE FROM message_pins WHERE chat_id = ? AND message_id = ?")
+            .bind(chat_id)
+            .bind(message_id)
+            .execute(executor)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    // 统计聊天室的置顶数
+    pub(crate) async fn count<'a, E>(chat_id: i64, executor: E) -> Result<i64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let (count,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM message_pins WHERE chat_id = ?")
+                .bind(chat_id)
+                .fetch_one(executor)
+                .await?;
+        Ok(count)
+    }
+
+    // 查询聊天室的置顶，最近置顶的在前
+    pub(crate) async fn list_by_chat_id<'a, E>(chat_id: i64, executor: E) -> Result<Vec<MessagePin>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let pins = sqlx::query_as(
+            "SELECT * FROM message_pins WHERE chat_id = ? ORDER BY created_at DESC, message_id DESC",
+        )
+        .bind(chat_id)
+        .fetch_all(executor)
+        .await?;
+        Ok(pins)
+    }
+
+    // 查询聊天室置顶的消息，最近置顶的在前
+    pub(crate) async fn list_messages<'a, E>(
+        chat_id: i64,
+        executor: E,
+    ) -> Result<Vec<PinnedMessage>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let messages = sqlx::query_as(
+            r#"
+            SELECT m.*, p.pinned_by, p.created_at AS pinned_at
+            FROM message_pins p
+            INNER JOIN messages m ON m.id = p.message_id
+            WHERE p.chat_id = ?
+            ORDER BY p.created_at DESC, p.message_id DESC
+            "#,
+        )
+        .bind(chat_id)
+        .fetch_all(executor)
+        .await?;
+        Ok(messages)
+    }
+
+    // 删除消息的置顶
+    pub(crate) async fn delete_by_message_id<'a, E>(message_id: i64, executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query("DELETE FROM message_pins WHERE message_id = ?")
+            .bind(message_id)
+            .execute(executor)
+            .await?;
+        Ok(res.rows_affected())
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, MySql, QueryBuilder};
+
+// 表情最大长度（字符数），和表字段长度一致，组合表情由多个字符组成
+const MAX_EMOJI_LEN: usize = 32;
+
+// 消息上某个表情的回应汇总
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+    // 当前用户是否回应了这个表情
+    pub reacted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub(crate) struct MessageReaction {
+    pub message_id: i64,
+    pub user_id: i64,
+    pub emoji: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MessageReaction {
+    // 添加回应，已经回应过时返回0
+    pub(crate) async fn add<'a, E>(
+        message_id: i64,
+        user_id: i64,
+        emoji: &str,
+        executor: E,
+    ) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            "INSERT IGNORE INTO message_reactions (message_id, user_id, emoji) VALUES (?, ?, ?)",
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .bind(emoji)
+        .execute(executor)
+        .await?;
+        Ok(res.rows_affected())
+    }
+
+    // 取消回应，没有回应过时返回0
+    pub(crate) async fn remove<'a, E>(
+        message_id: i64,
+        user_id: i64,
+        emoji: &str,
+        executor: E,
+    ) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            "DELETE FROM message_reactions WHERE message_id = ? AND user_id = ? AND emoji = ?",
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .bind(emoji)
+        .execute(executor)
+        .await?;
+        Ok(res.rows_affected())
+    }
+
+    // 统计消息上某个表情的回应数
+    pub(crate) async fn count<'a, E>(message_id: i64, emoji: &str, executor: E) -> Result<i64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM message_reactions WHERE message_id = ? AND emoji = ?",
+        )
+        .bind(message_id)
+        .bind(emoji)
+        .fetch_one(executor)
+        .await?;
+        Ok(count)
+    }
+
+    // 按消息汇总回应，表情按第一次回应的时间排序
+    pub(crate) async fn list_by_message_ids<'a, E>(
+        message_ids: &[i64],
+        user_id: i64,
+        executor: E,
+    ) -> Result<HashMap<i64, Vec<ReactionCount>>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let mut map: HashMap<i64, Vec<ReactionCount>> = HashMap::new();
+        if message_ids.is_empty() {
+            return Ok(map);
+        }
+        let mut query_builder =
+            QueryBuilder::new("SELECT message_id, emoji, COUNT(*), CAST(SUM(user_id = ");
+        query_builder
+            .push_bind(user_id)
+            .push(") AS SIGNED) FROM message_reactions WHERE message_id IN ");
+        query_builder.push_tuples(message_ids, |mut b, id| {
+            b.push_bind(id);
+        });
+        query_builder.push(" GROUP BY message_id, emoji ORDER BY MIN(created_at), emoji");
+        let rows: Vec<(i64, String, i64, i64)> =
+            query_builder.build_query_as().fetch_all(executor).await?;
+        for (message_id, emoji, count, reacted) in rows {
+            map.entry(message_id).or_default().push(ReactionCount {
+                emoji,
+                count,
+                reacted: reacted > 0,
+            });
+        }
+        Ok(map)
+    }
+
+    // 删除消息的所有回应
+    pub(crate) async fn delete_by_message_id<'a, E>(message_id: i64, executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query("DELETE FROM message_reactions WHERE message_id = ?")
+            .bind(message_id)
+            .execute(executor)
+            .await?;
+        Ok(res.rows_affected())
+    }
+}
+
+// 表情不能为空，不能包含空白和控制字符
+pub(crate) fn is_valid_emoji(emoji: &str) -> bool {
+    let len = emoji.chars().count();
+    len > 0 && len <= MAX_EMOJI_LEN && !emoji.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_emoji() {
+        assert!(is_valid_emoji("👍"));
+        assert!(is_valid_emoji("👨‍👩‍👧"));
+        assert!(is_valid_emoji(":thumbsup:"));
+        assert!(!is_valid_emoji(""));
+        assert!(!is_valid_emoji("a b"));
+        assert!(!is_valid_emoji("\n"));
+        assert!(!is_valid_emoji(&"👍".repeat(40)));
+    }
+}
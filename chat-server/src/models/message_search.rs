@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::message::Message;
+
+// 搜索词的最大数量
+const MAX_SEARCH_TERMS: usize = 10;
+// 摘要的最大字符数
+const SNIPPET_LEN: usize = 100;
+// 摘要中第一个命中位置之前保留的字符数
+const SNIPPET_CONTEXT: usize = 30;
+// 全文检索布尔模式中的操作符
+const BOOLEAN_OPERATORS: &[char] = &['+', '-', '<', '>', '(', ')', '~', '*', '"', '@'];
+
+// 消息搜索条件，只搜索用户所在的聊天室
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MessageSearch {
+    // 搜索词，空白分隔的多个词需要同时命中
+    pub terms: Vec<String>,
+    pub chat_id: Option<i64>,
+    pub sender_id: Option<i64>,
+    // 发送时间范围，左闭右开
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub has_attachment: Option<bool>,
+    // 分页游标，上一页最后一条消息的ID
+    pub before: Option<i64>,
+}
+
+// 搜索结果，highlights 是命中词在摘要中的字符区间 [start, end)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SearchHit {
+    pub message: Message,
+    pub snippet: String,
+    pub highlights: Vec<(usize, usize)>,
+}
+
+// 搜索结果按消息ID倒序，next_cursor 为空表示没有更多结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct SearchPage {
+    pub hits: Vec<SearchHit>,
+    pub next_cursor: Option<i64>,
+}
+
+impl MessageSearch {
+    // 拆分搜索词，去掉全文检索的操作符
+    pub(crate) fn parse_terms(query: &str) -> Vec<String> {
+        query
+            .split_whitespace()
+            .map(|term| term.replace(BOOLEAN_OPERATORS, ""))
+            .filter(|term| !term.is_empty())
+            .take(MAX_SEARCH_TERMS)
+            .collect()
+    }
+
+    // 布尔模式的检索式，每个词作为短语必须出现
+    pub(crate) fn boolean_query(&self) -> String {
+        self.terms
+            .iter()
+            .map(|term| format!("+\"{}\"", term))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl SearchHit {
+    pub(crate) fn new(message: Message, terms: &[String]) -> Self {
+        let (snippet, highlights) = highlight(&message.content, terms);
+        Self {
+            message,
+            snippet,
+            highlights,
+        }
+    }
+}
+
+// 截取第一个命中位置附近的摘要，并标出摘要中所有命中词的位置，忽略大小写
+pub(crate) fn highlight(content: &str, terms: &[String]) -> (String, Vec<(usize, usize)>) {
+    let chars: Vec<char> = content.chars().collect();
+    let terms: Vec<Vec<char>> = terms.iter().map(|t| t.chars().collect()).collect();
+    let mut ranges = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let len = terms
+            .iter()
+            .filter(|term| {
+                !term.is_empty()
+                    && chars.len() - i >= term.len()
+                    && term
+                        .iter()
+                        .zip(&chars[i..])
+                        .all(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+            })
+            .map(|term| term.len())
+            .max();
+        match len {
+            Some(len) => {
+                ranges.push((i, i + len));
+                i += len;
+            }
+            None => i += 1,
+        }
+    }
+    let start = ranges
+        .first()
+        .map(|(s, _)| s.saturating_sub(SNIPPET_CONTEXT))
+        .unwrap_or(0);
+    let end = (start + SNIPPET_LEN).min(chars.len());
+    let snippet = chars[start..end].iter().collect();
+    let highlights = ranges
+        .into_iter()
+        .filter(|(s, _)| *s < end)
+        .map(|(s, e)| (s - start, e.min(end) - start))
+        .collect();
+    (snippet, highlights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_terms() {
+        assert_eq!(
+            MessageSearch::parse_terms("  周会 +\"纪要\" -x* "),
+            vec!["周会", "纪要", "x"]
+        );
+        assert!(MessageSearch::parse_terms(" \"\" + ").is_empty());
+        let search = MessageSearch {
+            terms: MessageSearch::parse_terms("周会 纪要"),
+            ..Default::default()
+        };
+        assert_eq!(search.boolean_query(), "+\"周会\" +\"纪要\"");
+    }
+
+    #[test]
+    fn test_highlight() {
+        let terms = vec!["周会".to_string(), "rust".to_string()];
+        let (snippet, highlights) = highlight("明天的周会讨论 Rust 和 RUST", &terms);
+        assert_eq!(snippet, "明天的周会讨论 Rust 和 RUST");
+        assert_eq!(highlights, vec![(3, 5), (8, 12), (15, 19)]);
+
+        // 长文本从第一个命中位置之前截取
+        let content = format!("{}周会{}", "a".repeat(100), "b".repeat(100));
+        let (snippet, highlights) = highlight(&content, &terms);
+        assert_eq!(snippet.chars().count(), SNIPPET_LEN);
+        assert!(snippet.starts_with(&"a".repeat(SNIPPET_CONTEXT)));
+        assert_eq!(highlights, vec![(SNIPPET_CONTEXT, SNIPPET_CONTEXT + 2)]);
+
+        let (snippet, highlights) = highlight("没有命中", &terms);
+        assert_eq!(snippet, "没有命中");
+        assert!(highlights.is_empty());
+    }
+}
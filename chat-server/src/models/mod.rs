@@ -1,6 +1,17 @@
+pub(crate) mod attachment;
 pub(crate) mod chat;
 pub(crate) mod chat_members;
 pub(crate) mod message;
+pub(crate) mod message_edit;
+pub(crate) mod message_mention;
+pub(crate) mod message_pin;
+pub(crate) mod message_reaction;
+pub(crate) mod message_search;
 pub(crate) mod outbox_message;
+pub(crate) mod refresh_token;
+pub(crate) mod role;
+pub(crate) mod thumbnail;
+pub(crate) mod upload;
+pub(crate) mod upload_session;
 pub(crate) mod user;
 pub(crate) mod workspace;
@@ -0,0 +1,131 @@
+use anyhow::Result;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{prelude::FromRow, MySql, QueryBuilder};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub(crate) struct RefreshToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub family_id: String,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 登录或刷新后返回给客户端的令牌对
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct TokenPair {
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: u64,
+}
+
+impl RefreshToken {
+    // 生成一个随机的不透明令牌（32字节，hex编码）
+    pub(crate) fn generate() -> String {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // 计算令牌的sha256，数据库中只保存hash
+    pub(crate) fn hash(token: &str) -> String {
+        format!("{:x}", Sha256::digest(token.as_bytes()))
+    }
+
+    // 创建刷新令牌
+    pub(crate) async fn create<'a, E>(
+        user_id: i64,
+        family_id: &str,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+        executor: E,
+    ) -> Result<i64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            "INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(user_id)
+        .bind(family_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(executor)
+        .await?;
+        Ok(res.last_insert_id() as i64)
+    }
+
+    // 根据令牌hash查询
+    pub(crate) async fn find_by_hash<'a, E>(
+        token_hash: &str,
+        lock: bool,
+        executor: E,
+    ) -> Result<Option<RefreshToken>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let mut query_builder =
+            QueryBuilder::new("SELECT * FROM refresh_tokens WHERE token_hash = ");
+        query_builder.push_bind(token_hash);
+        if lock {
+            query_builder.push(" FOR UPDATE");
+        }
+
+        let res = query_builder
+            .build_query_as()
+            .fetch_optional(executor)
+            .await?;
+        Ok(res)
+    }
+
+    // 标记令牌已轮换
+    pub(crate) async fn mark_used<'a, E>(id: i64, executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query("UPDATE refresh_tokens SET used_at = ? WHERE id = ?")
+            .bind(Utc::now())
+            .bind(id)
+            .execute(executor)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    // 吊销整个令牌族
+    pub(crate) async fn revoke_family<'a, E>(family_id: &str, executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = ? WHERE family_id = ? AND revoked_at IS NULL",
+        )
+        .bind(Utc::now())
+        .bind(family_id)
+        .execute(executor)
+        .await?;
+        Ok(res.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_hash() {
+        let token = RefreshToken::generate();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, RefreshToken::generate());
+
+        let hash = RefreshToken::hash(&token);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, RefreshToken::hash(&token));
+        assert_ne!(hash, token);
+    }
+}
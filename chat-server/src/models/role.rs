@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{encode::IsNull, mysql::MySqlTypeInfo, prelude::Type, Decode, Encode, MySql};
+
+// 空间角色，按权限从低到高排列，可以直接比较大小
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum WorkspaceRole {
+    Guest,
+    #[default]
+    Member,
+    Admin,
+    Owner,
+}
+
+// 聊天室角色，按权限从低到高排列，可以直接比较大小
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ChatRole {
+    #[default]
+    Member,
+    Admin,
+    Owner,
+}
+
+impl Type<MySql> for WorkspaceRole {
+    fn type_info() -> MySqlTypeInfo {
+        <&str as Type<MySql>>::type_info()
+    }
+
+    fn compatible(ty: &MySqlTypeInfo) -> bool {
+        <&str as Type<MySql>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, MySql> for WorkspaceRole {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <MySql as sqlx::Database>::ArgumentBuffer<'_>,
+    ) -> std::result::Result<IsNull, sqlx::error::BoxDynError> {
+        let s: &str = self.into();
+        <&str as Encode<MySql>>::encode(s, buf)
+    }
+}
+
+impl<'r> Decode<'r, MySql> for WorkspaceRole {
+    fn decode(
+        value: <MySql as sqlx::Database>::ValueRef<'r>,
+    ) -> std::result::Result<Self, sqlx::error::BoxDynError> {
+        let s: &str = <&str as Decode<MySql>>::decode(value)?;
+        match s {
+            "guest" => std::result::Result::Ok(WorkspaceRole::Guest),
+            "member" => std::result::Result::Ok(WorkspaceRole::Member),
+            "admin" => std::result::Result::Ok(WorkspaceRole::Admin),
+            "owner" => std::result::Result::Ok(WorkspaceRole::Owner),
+            _ => Err(format!("unknown WorkspaceRole variant: '{}'", s).into()),
+        }
+    }
+}
+
+impl From<&WorkspaceRole> for &'static str {
+    fn from(t: &WorkspaceRole) -> Self {
+        match t {
+            WorkspaceRole::Guest => "guest",
+            WorkspaceRole::Member => "member",
+            WorkspaceRole::Admin => "admin",
+            WorkspaceRole::Owner => "owner",
+        }
+    }
+}
+
+impl Type<MySql> for ChatRole {
+    fn type_info() -> MySqlTypeInfo {
+        <&str as Type<MySql>>::type_info()
+    }
+
+    fn compatible(ty: &MySqlTypeInfo) -> bool {
+        <&str as Type<MySql>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, MySql> for ChatRole {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <MySql as sqlx::Database>::ArgumentBuffer<'_>,
+    ) -> std::result::Result<IsNull, sqlx::error::BoxDynError> {
+        let s: &str = self.into();
+        <&str as Encode<MySql>>::encode(s, buf)
+    }
+}
+
+impl<'r> Decode<'r, MySql> for ChatRole {
+    fn decode(
+        value: <MySql as sqlx::Database>::ValueRef<'r>,
+    ) -> std::result::Result<Self, sqlx::error::BoxDynError> {
+        let s: &str = <&str as Decode<MySql>>::decode(value)?;
+        match s {
+            "member" => std::result::Result::Ok(ChatRole::Member),
+            "admin" => std::result::Result::Ok(ChatRole::Admin),
+            "owner" => std::result::Result::Ok(ChatRole::Owner),
+            _ => Err(format!("unknown ChatRole variant: '{}'", s).into()),
+        }
+    }
+}
+
+impl From<&ChatRole> for &'static str {
+    fn from(t: &ChatRole) -> Self {
+        match t {
+            ChatRole::Member => "member",
+            ChatRole::Admin => "admin",
+            ChatRole::Owner => "owner",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_order() {
+        assert!(WorkspaceRole::Owner > WorkspaceRole::Admin);
+        assert!(WorkspaceRole::Admin > WorkspaceRole::Member);
+        assert!(WorkspaceRole::Member > WorkspaceRole::Guest);
+        assert!(ChatRole::Owner > ChatRole::Admin);
+        assert!(ChatRole::Admin > ChatRole::Member);
+    }
+}
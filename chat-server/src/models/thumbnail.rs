@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chat_core::event;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, MySql, QueryBuilder};
+
+use crate::storage;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub(crate) struct Thumbnail {
+    pub id: i64,
+    pub source_key: String,
+    pub max_size: i32,
+    pub storage_key: String,
+    pub size: i64,
+    pub mime_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ThumbnailCreate {
+    pub source_key: String,
+    pub max_size: i32,
+    pub storage_key: String,
+    pub size: i64,
+    pub mime_type: String,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Thumbnail {
+    // 保存缩略图记录，同一张原图同一尺寸只保存一次
+    pub(crate) async fn create<'a, E>(data: ThumbnailCreate, executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            r#"
+            INSERT IGNORE INTO thumbnails (source_key, max_size, storage_key, size, mime_type, width, height)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(data.source_key)
+        .bind(data.max_size)
+        .bind(data.storage_key)
+        .bind(data.size)
+        .bind(data.mime_type)
+        .bind(data.width)
+        .bind(data.height)
+        .execute(executor)
+        .await?;
+        Ok(res.rows_affected())
+    }
+
+    // 原图是否已经生成过缩略图
+    pub(crate) async fn exists_for_source<'a, E>(source_key: &str, executor: E) -> Result<bool>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res: Option<(i64,)> =
+            sqlx::query_as("SELECT id FROM thumbnails WHERE source_key = ? LIMIT 1")
+                .bind(source_key)
+                .fetch_optional(executor)
+                .await?;
+        Ok(res.is_some())
+    }
+
+    // 查询多张原图的缩略图，按原图key分组，尺寸从小到大
+    pub(crate) async fn list_by_source_keys<'a, E>(
+        source_keys: &[&str],
+        executor: E,
+    ) -> Result<HashMap<String, Vec<Thumbnail>>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let mut map: HashMap<String, Vec<Thumbnail>> = HashMap::new();
+        if source_keys.is_empty() {
+            return Ok(map);
+        }
+        let mut query_builder = QueryBuilder::new("SELECT * FROM thumbnails WHERE source_key IN ");
+        query_builder.push_tuples(source_keys, |mut b, key| {
+            b.push_bind(*key);
+        });
+        query_builder.push(" ORDER BY source_key, max_size");
+        let thumbnails: Vec<Thumbnail> = query_builder.build_query_as().fetch_all(executor).await?;
+        for thumbnail in thumbnails {
+            map.entry(thumbnail.source_key.clone())
+                .or_default()
+                .push(thumbnail);
+        }
+        Ok(map)
+    }
+
+    // 根据缩略图的存储key查询
+    pub(crate) async fn find_by_storage_key<'a, E>(
+        storage_key: &str,
+        executor: E,
+    ) -> Result<Option<Thumbnail>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query_as::<_, Thumbnail>(
+            "SELECT * FROM thumbnails WHERE storage_key = ? ORDER BY id LIMIT 1",
+        )
+        .bind(storage_key)
+        .fetch_optional(executor)
+        .await?;
+        Ok(res)
+    }
+
+    // 删除原图的缩略图记录
+    pub(crate) async fn delete_by_source_key<'a, E>(source_key: &str, executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query("DELETE FROM thumbnails WHERE source_key = ?")
+            .bind(source_key)
+            .execute(executor)
+            .await?;
+        Ok(res.rows_affected())
+    }
+}
+
+impl Thumbnail {
+    // 转换为事件中的缩略图，base_url 为文件下载地址前缀
+    pub(crate) fn to_event(&self, base_url: &str) -> event::Thumbnail {
+        event::Thumbnail {
+            url: storage::file_url(base_url, &self.storage_key),
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
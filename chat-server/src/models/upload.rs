@@ -0,0 +1,323 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    encode::IsNull, mysql::MySqlTypeInfo, prelude::FromRow, prelude::Type, Decode, Encode, MySql,
+    QueryBuilder,
+};
+
+use crate::storage;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub(crate) struct Upload {
+    pub id: i64,
+    pub ws_id: i64,
+    pub chat_id: i64,
+    pub uploader_id: i64,
+    pub storage_key: String,
+    pub filename: String,
+    pub size: i64,
+    pub mime_type: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub status: UploadStatus,
+    // 直传未确认时的过期时间，确认后为空
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 上传状态，直传的文件确认之后才能被消息引用
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum UploadStatus {
+    Pending,
+    #[default]
+    Completed,
+}
+
+// 预签名直传的信息，客户端用 PUT 请求把文件上传到 url，上传时带上 content_type，
+// 文件大小必须和申请时一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PresignedUpload {
+    pub upload_id: i64,
+    pub url: String,
+    pub key: String,
+    pub content_type: String,
+    pub expires_in: u64,
+}
+
+// 上传成功后返回给客户端的文件信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct UploadedFile {
+    pub url: String,
+    pub key: String,
+    pub filename: String,
+    pub size: i64,
+    pub mime_type: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct UploadCreate {
+    pub ws_id: i64,
+    pub chat_id: i64,
+    pub uploader_id: i64,
+    pub storage_key: String,
+    pub filename: String,
+    pub size: i64,
+    pub mime_type: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub status: UploadStatus,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Upload {
+    // 记录上传文件
+    pub(crate) async fn create<'a, E>(data: UploadCreate, executor: E) -> Result<i64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            r#"
+            INSERT INTO uploads (ws_id, chat_id, uploader_id, storage_key, filename, size, mime_type, width, height, status, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(data.ws_id)
+        .bind(data.chat_id)
+        .bind(data.uploader_id)
+        .bind(data.storage_key)
+        .bind(data.filename)
+        .bind(data.size)
+        .bind(data.mime_type)
+        .bind(data.width)
+        .bind(data.height)
+        .bind(data.status)
+        .bind(data.expires_at)
+        .execute(executor)
+        .await?;
+        Ok(res.last_insert_id() as i64)
+    }
+
+    // 根据ID查询
+    pub(crate) async fn find_by_id<'a, E>(
+        id: i64,
+        lock: bool,
+        executor: E,
+    ) -> Result<Option<Upload>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let mut query_builder = QueryBuilder::new("SELECT * FROM uploads WHERE id = ");
+        query_builder.push_bind(id);
+        if lock {
+            query_builder.push(" FOR UPDATE");
+        }
+        let res = query_builder
+            .build_query_as()
+            .fetch_optional(executor)
+            .await?;
+        Ok(res)
+    }
+
+    // 确认直传的文件已经上传完成，storage_key 是文件复制后的位置
+    pub(crate) async fn complete<'a, E>(
+        id: i64,
+        storage_key: &str,
+        width: Option<i32>,
+        height: Option<i32>,
+        executor: E,
+    ) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query(
+            "UPDATE uploads SET status = 'completed', storage_key = ?, width = ?, height = ?, expires_at = NULL WHERE id = ? AND status = 'pending'",
+        )
+        .bind(storage_key)
+        .bind(width)
+        .bind(height)
+        .bind(id)
+        .execute(executor)
+        .await?;
+        Ok(res.rows_affected())
+    }
+
+    // 查询聊天室中上传的文件
+    pub(crate) async fn find_in_chat<'a, E>(
+        chat_id: i64,
+        storage_key: &str,
+        executor: E,
+    ) -> Result<Option<Upload>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query_as::<_, Upload>(
+            "SELECT * FROM uploads WHERE chat_id = ? AND storage_key = ? AND status = 'completed' ORDER BY id LIMIT 1",
+        )
+        .bind(chat_id)
+        .bind(storage_key)
+        .fetch_optional(executor)
+        .await?;
+        Ok(res)
+    }
+
+    // 查询空间中超过保留期限的上传文件
+    pub(crate) async fn list_expired<'a, E>(
+        ws_id: i64,
+        before: DateTime<Utc>,
+        limit: i64,
+        executor: E,
+    ) -> Result<Vec<Upload>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query_as::<_, Upload>(
+            "SELECT * FROM uploads WHERE ws_id = ? AND created_at < ? AND status = 'completed' ORDER BY id LIMIT ?",
+        )
+        .bind(ws_id)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(executor)
+        .await?;
+        Ok(res)
+    }
+
+    // 查询超过有效期还没有确认的直传记录
+    pub(crate) async fn list_pending_expired<'a, E>(
+        now: DateTime<Utc>,
+        limit: i64,
+        executor: E,
+    ) -> Result<Vec<Upload>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query_as::<_, Upload>(
+            "SELECT * FROM uploads WHERE status = 'pending' AND expires_at < ? ORDER BY id LIMIT ?",
+        )
+        .bind(now)
+        .bind(limit)
+        .fetch_all(executor)
+        .await?;
+        Ok(res)
+    }
+
+    // 删除还没有确认的直传记录，已经确认的不删除
+    pub(crate) async fn delete_pending<'a, E>(id: i64, executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query("DELETE FROM uploads WHERE id = ? AND status = 'pending'")
+            .bind(id)
+            .execute(executor)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    // 删除上传记录
+    pub(crate) async fn delete<'a, E>(id: i64, executor: E) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query("DELETE FROM uploads WHERE id = ?")
+            .bind(id)
+            .execute(executor)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    // 聊天室中同一个文件的上传记录数量
+    pub(crate) async fn count_in_chat<'a, E>(
+        chat_id: i64,
+        storage_key: &str,
+        executor: E,
+    ) -> Result<i64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let (count,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM uploads WHERE chat_id = ? AND storage_key = ?")
+                .bind(chat_id)
+                .bind(storage_key)
+                .fetch_one(executor)
+                .await?;
+        Ok(count)
+    }
+
+    // 存储key是否还有上传记录或附件在使用
+    pub(crate) async fn is_key_in_use<'a, E>(storage_key: &str, executor: E) -> Result<bool>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let (in_use,): (bool,) = sqlx::query_as(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM uploads WHERE storage_key = ?)
+                OR EXISTS (SELECT 1 FROM attachments WHERE storage_key = ?)
+            "#,
+        )
+        .bind(storage_key)
+        .bind(storage_key)
+        .fetch_one(executor)
+        .await?;
+        Ok(in_use)
+    }
+}
+
+impl UploadedFile {
+    // base_url 为文件下载地址前缀
+    pub(crate) fn new(value: Upload, base_url: &str) -> Self {
+        Self {
+            url: storage::file_url(base_url, &value.storage_key),
+            key: value.storage_key,
+            filename: value.filename,
+            size: value.size,
+            mime_type: value.mime_type,
+            width: value.width,
+            height: value.height,
+        }
+    }
+}
+
+impl Type<MySql> for UploadStatus {
+    fn type_info() -> MySqlTypeInfo {
+        <&str as Type<MySql>>::type_info()
+    }
+
+    fn compatible(ty: &MySqlTypeInfo) -> bool {
+        <&str as Type<MySql>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, MySql> for UploadStatus {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <MySql as sqlx::Database>::ArgumentBuffer<'_>,
+    ) -> std::result::Result<IsNull, sqlx::error::BoxDynError> {
+        let s: &str = self.into();
+        <&str as Encode<MySql>>::encode(s, buf)
+    }
+}
+
+impl<'r> Decode<'r, MySql> for UploadStatus {
+    fn decode(
+        value: <MySql as sqlx::Database>::ValueRef<'r>,
+    ) -> std::result::Result<Self, sqlx::error::BoxDynError> {
+        let s: &str = <&str as Decode<MySql>>::decode(value)?;
+        match s {
+            "pending" => std::result::Result::Ok(UploadStatus::Pending),
+            "completed" => std::result::Result::Ok(UploadStatus::Completed),
+            _ => Err(format!("unknown UploadStatus variant: '{}'", s).into()),
+        }
+    }
+}
+
+impl From<&UploadStatus> for &'static str {
+    fn from(t: &UploadStatus) -> Self {
+        match t {
+            UploadStatus::Pending => "pending",
+            UploadStatus::Completed => "completed",
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, MySql, QueryBuilder};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub(crate) struct UploadSession {
+    pub id: String,
+    pub ws_id: i64,
+    pub chat_id: i64,
+    pub uploader_id: i64,
+    pub filename: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub chunk_size: i64,
+    pub chunk_count: i32,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 返回给客户端的会话状态，received_chunks 是已经收到的分片序号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct UploadSessionStatus {
+    pub session_id: String,
+    pub size: i64,
+    pub chunk_size: i64,
+    pub chunk_count: i32,
+    pub received_chunks: Vec<i32>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl UploadSession {
+    // 分片序号对应的分片大小，最后一片可能比较小
+    pub(crate) fn expected_chunk_size(&self, index: i32) -> Option<i64> {
+        if index < 0 || index >= self.chunk_count {
+            return None;
+        }
+        if index == self.chunk_count - 1 {
+            Some(self.size - self.chunk_size * (self.chunk_count as i64 - 1))
+        } else {
+            Some(self.chunk_size)
+        }
+    }
+
+    // 创建会话
+    pub(crate) async fn create<'a, E>(session: &UploadSession, executor: E) -> Result<()>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO upload_sessions
+            (id, ws_id, chat_id, uploader_id, filename, mime_type, size, chunk_size, chunk_count, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&session.id)
+        .bind(session.ws_id)
+        .bind(session.chat_id)
+        .bind(session.uploader_id)
+        .bind(&session.filename)
+        .bind(&session.mime_type)
+        .bind(session.size)
+        .bind(session.chunk_size)
+        .bind(session.chunk_count)
+        .bind(session.expires_at)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    // 根据ID查询
+    pub(crate) async fn find_by_id<'a, E>(
+        id: &str,
+        lock: bool,
+        executor: E,
+    ) -> Result<Option<UploadSession>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let mut query_builder = QueryBuilder::new("SELECT * FROM upload_sessions WHERE id = ");
+        query_builder.push_bind(id);
+        if lock {
+            query_builder.push(" FOR UPDATE");
+        }
+        let res = query_builder
+            .build_query_as()
+            .fetch_optional(executor)
+            .await?;
+        Ok(res)
+    }
+
+    // 记录收到的分片，重复上传同一分片时覆盖
+    pub(crate) async fn add_chunk<'a, E>(id: &str, index: i32, size: i64, executor: E) -> Result<()>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO upload_session_chunks (session_id, chunk_index, size) VALUES (?, ?, ?)
+            ON DUPLICATE KEY UPDATE size = VALUES(size)
+            "#,
+        )
+        .bind(id)
+        .bind(index)
+        .bind(size)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    // 查询已经收到的分片序号
+    pub(crate) async fn list_chunks<'a, E>(id: &str, executor: E) -> Result<Vec<i32>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let rows: Vec<(i32,)> = sqlx::query_as(
+            "SELECT chunk_index FROM upload_session_chunks WHERE session_id = ? ORDER BY chunk_index",
+        )
+        .bind(id)
+        .fetch_all(executor)
+        .await?;
+        Ok(rows.into_iter().map(|(index,)| index).collect())
+    }
+
+    // 延长会话的过期时间
+    pub(crate) async fn touch<'a, E>(id: &str, expires_at: DateTime<Utc>, executor: E) -> Result<()>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        sqlx::query("UPDATE upload_sessions SET expires_at = ? WHERE id = ?")
+            .bind(expires_at)
+            .bind(id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+
+    // 删除会话和分片记录
+    pub(crate) async fn delete(id: &str, tx: &mut sqlx::Transaction<'_, MySql>) -> Result<()> {
+        sqlx::query("DELETE FROM upload_session_chunks WHERE session_id = ?")
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DELETE FROM upload_sessions WHERE id = ?")
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    // 查询已经过期的会话ID
+    pub(crate) async fn list_expired<'a, E>(
+        now: DateTime<Utc>,
+        limit: i64,
+        executor: E,
+    ) -> Result<Vec<String>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let rows: Vec<(String,)> =
+            sqlx::query_as("SELECT id FROM upload_sessions WHERE expires_at < ? LIMIT ?")
+                .bind(now)
+                .bind(limit)
+                .fetch_all(executor)
+                .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_chunk_size() {
+        let session = UploadSession {
+            id: "s".to_string(),
+            ws_id: 1,
+            chat_id: 1,
+            uploader_id: 1,
+            filename: "a.mp4".to_string(),
+            mime_type: "video/mp4".to_string(),
+            size: 25,
+            chunk_size: 10,
+            chunk_count: 3,
+            expires_at: Utc::now(),
+            created_at: Utc::now(),
+        };
+        assert_eq!(session.expected_chunk_size(0), Some(10));
+        assert_eq!(session.expected_chunk_size(1), Some(10));
+        assert_eq!(session.expected_chunk_size(2), Some(5));
+        assert_eq!(session.expected_chunk_size(3), None);
+        assert_eq!(session.expected_chunk_size(-1), None);
+    }
+}
@@ -5,10 +5,12 @@ use argon2::{
 };
 use chat_core::models::user::CurUser;
 use serde::{Deserialize, Serialize};
+
+use crate::models::role::WorkspaceRole;
 use sqlx::{
     prelude::FromRow,
     types::chrono::{DateTime, Utc},
-    MySql, Pool,
+    MySql, Pool, QueryBuilder,
 };
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
@@ -20,6 +22,7 @@ pub(crate) struct User {
     #[serde(skip)]
     pub password: String,
     pub email: String,
+    pub ws_role: WorkspaceRole,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -76,6 +79,38 @@ impl User {
         Ok(res)
     }
 
+    // 根据用户ID批量查询用户
+    pub(crate) async fn list_by_ids(ids: &[i64], pool: &Pool<MySql>) -> Result<Vec<User>> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut query_builder = QueryBuilder::new("select * from users where id in ");
+        query_builder.push_tuples(ids, |mut b, id| {
+            b.push_bind(id);
+        });
+        let res = query_builder.build_query_as().fetch_all(pool).await?;
+
+        Ok(res)
+    }
+
+    // 修改用户的空间角色
+    pub(crate) async fn update_ws_role<'a, E>(
+        id: i64,
+        ws_role: WorkspaceRole,
+        executor: E,
+    ) -> Result<u64>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query("update users set ws_role = ? where id = ?")
+            .bind(ws_role)
+            .bind(id)
+            .execute(executor)
+            .await?;
+
+        Ok(res.rows_affected())
+    }
+
     // 使用argon2算法hash原始密码
     pub(crate) fn hash_password(password: &String) -> Result<String> {
         let salt = SaltString::generate(&mut OsRng);
@@ -9,6 +9,9 @@ pub(crate) struct Workspace {
     pub name: String,
     pub description: String,
     pub owner_id: i64,
+    pub storage_used: i64,
+    pub storage_quota: Option<i64>,
+    pub retention_days: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -42,11 +45,14 @@ impl Workspace {
         Ok(res)
     }
     // 变更工作空间拥有者
-    pub(crate) async fn change_owner(id: i64, owner_id: i64, pool: &Pool<MySql>) -> Result<bool> {
+    pub(crate) async fn change_owner<'a, E>(id: i64, owner_id: i64, executor: E) -> Result<bool>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
         let res = sqlx::query("UPDATE workspaces SET owner_id = ? WHERE id = ?")
             .bind(owner_id)
             .bind(id)
-            .execute(pool)
+            .execute(executor)
             .await?;
         Ok(res.rows_affected() > 0)
     }
@@ -81,4 +87,53 @@ impl Workspace {
             .await?;
         Ok(res)
     }
+
+    // 增加或减少空间已使用的存储
+    pub(crate) async fn add_storage_used<'a, E>(id: i64, delta: i64, executor: E) -> Result<()>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        sqlx::query(
+            "UPDATE workspaces SET storage_used = GREATEST(storage_used + ?, 0) WHERE id = ?",
+        )
+        .bind(delta)
+        .bind(id)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    // 设置附件保留天数，None 表示使用默认值
+    pub(crate) async fn set_retention_days<'a, E>(
+        id: i64,
+        retention_days: Option<i32>,
+        executor: E,
+    ) -> Result<()>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        sqlx::query("UPDATE workspaces SET retention_days = ? WHERE id = ?")
+            .bind(retention_days)
+            .bind(id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+
+    // 查询需要清理附件的空间和保留天数
+    pub(crate) async fn list_retention<'a, E>(
+        default_days: i64,
+        executor: E,
+    ) -> Result<Vec<(i64, i64)>>
+    where
+        E: sqlx::Executor<'a, Database = MySql>,
+    {
+        let res = sqlx::query_as(
+            "SELECT id, CAST(COALESCE(retention_days, ?) AS SIGNED) AS days FROM workspaces HAVING days > 0",
+        )
+        .bind(default_days)
+        .fetch_all(executor)
+        .await?;
+        Ok(res)
+    }
 }
@@ -1,20 +1,31 @@
-use crate::handler::{auth, chat, message, workspace};
+use crate::handler::{auth, chat, file, message, workspace};
 use crate::middlewares::set_common_layer;
 use crate::AppState;
 use anyhow::Result;
+use axum::extract::DefaultBodyLimit;
 use axum::http::Method;
 use axum::middleware::from_fn_with_state;
-use axum::{routing::post, Router};
+use axum::{
+    routing::{get, post, put},
+    Router,
+};
 use chat_core::middlewares::auth::verify_token;
 use tower_http::cors::{self, CorsLayer};
 
 pub fn get_router(state: AppState) -> Result<Router> {
+    // 上传接口的请求体上限，在单个文件上限的基础上留出 multipart 头部的空间
+    let upload_body_limit = state.app_config.storage.max_file_size as usize + 64 * 1024;
+    let chunk_body_limit = state.app_config.storage.chunked.chunk_size as usize;
     let chat = Router::new().nest(
         "/chat",
         Router::new()
             .route("/create", post(chat::create))
             .route("/drop", post(chat::drop))
+            .route("/rename", post(chat::rename))
+            .route("/kick", post(chat::kick))
+            .route("/set_role", post(chat::set_role))
             .route("/list_by_user_id", post(chat::list_by_user_id))
+            .route("/mark_read", post(chat::mark_read))
             .route("/user_join", post(chat::user_join))
             .route("/user_leave", post(chat::user_leave)),
     );
@@ -23,18 +34,57 @@ pub fn get_router(state: AppState) -> Result<Router> {
         Router::new()
             .route("/recent", post(message::recent))
             .route("/send", post(message::send))
-            .route("/upload", post(message::upload)),
+            .route("/thread", post(message::thread))
+            .route("/edit", post(message::edit))
+            .route("/delete", post(message::delete))
+            .route("/history", post(message::history))
+            .route("/search", post(message::search))
+            .route("/pin", post(message::pin))
+            .route("/unpin", post(message::unpin))
+            .route("/pins", post(message::pins))
+            .route("/reaction/add", post(message::add_reaction))
+            .route("/reaction/remove", post(message::remove_reaction))
+            .route("/mentions/unread", post(message::unread_mentions))
+            .route("/mentions/read", post(message::read_mentions))
+            .route(
+                "/upload",
+                post(message::upload).layer(DefaultBodyLimit::max(upload_body_limit)),
+            )
+            .route("/presign_upload", post(message::presign_upload))
+            .route("/confirm_upload", post(message::confirm_upload))
+            .route(
+                "/upload_session/create",
+                post(message::create_upload_session),
+            )
+            .route(
+                "/upload_session/{session_id}/{index}",
+                put(message::put_chunk).layer(DefaultBodyLimit::max(chunk_body_limit)),
+            )
+            .route(
+                "/upload_session/status",
+                post(message::upload_session_status),
+            )
+            .route(
+                "/upload_session/finalize",
+                post(message::finalize_upload_session),
+            ),
     );
 
+    let file = Router::new().route("/files/{*key}", get(file::download));
+
     let workspace = Router::new().nest(
         "/workspace",
         Router::new()
             .route("/create", post(workspace::create))
-            .route("/change_owner", post(workspace::change_owner)),
+            .route("/change_owner", post(workspace::change_owner))
+            .route("/set_role", post(workspace::set_role))
+            .route("/set_retention", post(workspace::set_retention))
+            .route("/storage_usage", post(workspace::storage_usage)),
     );
 
     let protected_routes = chat
         .merge(message)
+        .merge(file)
         .merge(workspace)
         .layer(from_fn_with_state(state.clone(), verify_token::<AppState>));
 
@@ -44,6 +94,7 @@ pub fn get_router(state: AppState) -> Result<Router> {
             .route("/logout", post(auth::logout))
             .layer(from_fn_with_state(state.clone(), verify_token::<AppState>))
             .route("/signin", post(auth::signin))
+            .route("/refresh", post(auth::refresh))
             .route("/signup", post(auth::signup)),
     );
 
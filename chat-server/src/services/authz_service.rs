@@ -0,0 +1,87 @@
+use sqlx::{MySql, Pool};
+
+use chat_core::models::user::CurUser;
+
+use crate::{
+    error::AppError,
+    models::{
+        chat_members::ChatMembers,
+        role::{ChatRole, WorkspaceRole},
+        user::User,
+        workspace::Workspace,
+    },
+};
+
+// 权限校验，所有需要角色的操作都在这里判断
+#[derive(Debug)]
+pub(crate) struct AuthzService {
+    pub(crate) pool: Pool<MySql>,
+}
+
+impl AuthzService {
+    pub fn new(pool: Pool<MySql>) -> Self {
+        Self { pool }
+    }
+
+    // 查询用户在空间中的角色，空间拥有者由 workspaces.owner_id 决定
+    pub async fn workspace_role(
+        &self,
+        ws_id: i64,
+        user_id: i64,
+    ) -> Result<Option<WorkspaceRole>, AppError> {
+        let workspace = Workspace::find_by_id(ws_id, false, &self.pool)
+            .await?
+            .ok_or(AppError::WorkspaceNotFound)?;
+        if workspace.owner_id == user_id {
+            return Ok(Some(WorkspaceRole::Owner));
+        }
+        let role = User::find_by_id(user_id, &self.pool)
+            .await?
+            .filter(|u| u.ws_id == ws_id)
+            .map(|u| u.ws_role);
+        Ok(role)
+    }
+
+    // 校验用户在空间中的角色不低于 min_role
+    pub async fn require_workspace_role(
+        &self,
+        ws_id: i64,
+        user: &CurUser,
+        min_role: WorkspaceRole,
+    ) -> Result<WorkspaceRole, AppError> {
+        let role = self
+            .workspace_role(ws_id, user.id)
+            .await?
+            .ok_or(AppError::PermissionDenied)?;
+        if role < min_role {
+            return Err(AppError::PermissionDenied);
+        }
+        Ok(role)
+    }
+
+    // 查询用户在聊天室中的角色
+    pub async fn chat_role(
+        &self,
+        chat_id: i64,
+        user_id: i64,
+    ) -> Result<Option<ChatRole>, AppError> {
+        Ok(ChatMembers::find_role(chat_id, user_id, false, &self.pool).await?)
+    }
+
+    // 校验用户在聊天室中的角色不低于 min_role，不是成员时返回 UserNotInChat
+    pub async fn require_chat_role(
+        &self,
+        chat_id: i64,
+        user: &CurUser,
+        min_role: ChatRole,
+    ) -> Result<ChatRole, AppError> {
+        let role = self
+            .chat_role(chat_id, user.id)
+            .await?
+            .ok_or(AppError::UserNotInChat)?;
+        if role < min_role {
+            return Err(AppError::PermissionDenied);
+        }
+        Ok(role)
+    }
+}
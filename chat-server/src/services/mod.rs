@@ -1,6 +1,11 @@
+pub(crate) mod auth_service;
+pub(crate) mod authz_service;
 pub(crate) mod chat_service;
 pub(crate) mod message_publish_service;
 pub(crate) mod message_service;
 pub(crate) mod outbox_message_service;
+pub(crate) mod storage_service;
+pub(crate) mod thumbnail_service;
+pub(crate) mod upload_session_service;
 pub(crate) mod user_service;
 pub(crate) mod workspace_service;
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Pool};
+use tracing::{info, warn};
+
+use crate::{
+    error::AppError,
+    models::{
+        attachment::Attachment, message::Message, thumbnail::Thumbnail, upload::Upload,
+        workspace::Workspace,
+    },
+    storage::Storage,
+};
+
+// 每次清理的上传文件数量
+const CLEANUP_BATCH_SIZE: i64 = 100;
+
+// 空间的存储用量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StorageUsage {
+    pub used: i64,
+    // 0 表示不限制
+    pub quota: i64,
+    // 0 表示永久保留
+    pub retention_days: i32,
+}
+
+// 空间的存储配额和附件保留期限
+#[derive(Debug)]
+pub(crate) struct StorageService {
+    pub(crate) pool: Pool<MySql>,
+    pub(crate) storage: Arc<dyn Storage>,
+    pub(crate) default_quota: u64,
+    pub(crate) default_retention_days: u32,
+}
+
+impl StorageService {
+    pub(crate) fn new(
+        pool: Pool<MySql>,
+        storage: Arc<dyn Storage>,
+        default_quota: u64,
+        default_retention_days: u32,
+    ) -> Self {
+        Self {
+            pool,
+            storage,
+            default_quota,
+            default_retention_days,
+        }
+    }
+
+    // 查询空间的存储用量
+    pub(crate) async fn usage(&self, ws_id: i64) -> Result<StorageUsage, AppError> {
+        let ws = Workspace::find_by_id(ws_id, false, &self.pool)
+            .await?
+            .ok_or(AppError::WorkspaceNotFound)?;
+        Ok(StorageUsage {
+            used: ws.storage_used,
+            quota: self.quota_of(&ws),
+            retention_days: ws
+                .retention_days
+                .unwrap_or(self.default_retention_days as i32),
+        })
+    }
+
+    // 上传前预先检查配额，尽早拒绝
+    pub(crate) async fn check_quota(&self, ws_id: i64, size: u64) -> Result<(), AppError> {
+        let ws = Workspace::find_by_id(ws_id, false, &self.pool)
+            .await?
+            .ok_or(AppError::WorkspaceNotFound)?;
+        self.ensure_quota(&ws, size)
+    }
+
+    // 在事务中锁住空间，检查配额后记录用量
+    pub(crate) async fn charge(
+        &self,
+        ws_id: i64,
+        size: u64,
+        tx: &mut sqlx::Transaction<'_, MySql>,
+    ) -> Result<(), AppError> {
+        let ws = Workspace::find_by_id(ws_id, true, &mut **tx)
+            .await?
+            .ok_or(AppError::WorkspaceNotFound)?;
+        self.ensure_quota(&ws, size)?;
+        Workspace::add_storage_used(ws_id, size as i64, &mut **tx).await?;
+        Ok(())
+    }
+
+    // 清理超过保留期限的附件，返回清理的文件数量
+    pub(crate) async fn cleanup_expired_attachments(&self) -> Result<usize, AppError> {
+        let workspaces =
+            Workspace::list_retention(self.default_retention_days as i64, &self.pool).await?;
+        let mut total = 0;
+        for (ws_id, days) in workspaces {
+            let before = Utc::now() - Duration::days(days);
+            loop {
+                let uploads =
+                    Upload::list_expired(ws_id, before, CLEANUP_BATCH_SIZE, &self.pool).await?;
+                if uploads.is_empty() {
+                    break;
+                }
+                for upload in uploads {
+                    self.expire_upload(upload).await?;
+                    total += 1;
+                }
+            }
+        }
+        if total > 0 {
+            info!("cleaned up {} expired uploads", total);
+        }
+        Ok(total)
+    }
+
+    // 清理过期还没有确认的直传记录和已经上传的文件，退还预留的存储用量，返回清理的记录数
+    pub(crate) async fn cleanup_pending_uploads(&self) -> Result<usize, AppError> {
+        let mut total = 0;
+        loop {
+            let uploads =
+                Upload::list_pending_expired(Utc::now(), CLEANUP_BATCH_SIZE, &self.pool).await?;
+            if uploads.is_empty() {
+                break;
+            }
+            for upload in uploads {
+                // 已经被并发的确认请求完成
+                let mut tx = self.pool.begin().await?;
+                if Upload::delete_pending(upload.id, &mut *tx).await? == 0 {
+                    continue;
+                }
+                // 退还申请直传时预留的存储用量
+                Workspace::add_storage_used(upload.ws_id, -upload.size, &mut *tx).await?;
+                tx.commit().await?;
+                total += 1;
+                // 直传的key是随机生成的，不会被其他记录引用
+                if let Err(e) = self.storage.delete(&upload.storage_key).await {
+                    warn!(
+                        "delete pending upload {} failed: {:?}",
+                        upload.storage_key, e
+                    );
+                }
+            }
+        }
+        if total > 0 {
+            info!("cleaned up {} expired pending uploads", total);
+        }
+        Ok(total)
+    }
+
+    // 删除上传记录，聊天室中没有其他相同文件的上传记录时删除引用它的附件
+    async fn expire_upload(&self, upload: Upload) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        if Upload::delete(upload.id, &mut *tx).await? == 0 {
+            return Ok(());
+        }
+        if Upload::count_in_chat(upload.chat_id, &upload.storage_key, &mut *tx).await? == 0 {
+            let message_ids =
+                Attachment::delete_in_chat(upload.chat_id, &upload.storage_key, &mut tx).await?;
+            Message::mark_attachments_expired(&message_ids, &mut *tx).await?;
+        }
+        Workspace::add_storage_used(upload.ws_id, -upload.size, &mut *tx).await?;
+        tx.commit().await?;
+
+        // 相同内容的文件可能还被其他记录引用
+        if Upload::is_key_in_use(&upload.storage_key, &self.pool).await? {
+            return Ok(());
+        }
+        let thumbnails =
+            Thumbnail::list_by_source_keys(&[upload.storage_key.as_str()], &self.pool).await?;
+        for thumbnail in thumbnails.values().flatten() {
+            if let Err(e) = self.storage.delete(&thumbnail.storage_key).await {
+                warn!("delete thumbnail {} failed: {:?}", thumbnail.storage_key, e);
+            }
+        }
+        Thumbnail::delete_by_source_key(&upload.storage_key, &self.pool).await?;
+        self.storage.delete(&upload.storage_key).await?;
+        Ok(())
+    }
+
+    fn quota_of(&self, ws: &Workspace) -> i64 {
+        ws.storage_quota.unwrap_or(self.default_quota as i64)
+    }
+
+    fn ensure_quota(&self, ws: &Workspace, size: u64) -> Result<(), AppError> {
+        let quota = self.quota_of(ws);
+        if quota > 0 && ws.storage_used + size as i64 > quota {
+            return Err(AppError::StorageQuotaExceeded);
+        }
+        Ok(())
+    }
+}
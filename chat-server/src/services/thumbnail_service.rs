@@ -0,0 +1,108 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::body::Bytes;
+use sqlx::{MySql, Pool};
+use tracing::{info, warn};
+
+use crate::{
+    error::AppError,
+    models::thumbnail::{Thumbnail, ThumbnailCreate},
+    storage::{thumbnail, Storage},
+};
+
+#[derive(Debug)]
+pub(crate) struct ThumbnailService {
+    pub(crate) pool: Pool<MySql>,
+    pub(crate) storage: Arc<dyn Storage>,
+    pub(crate) sizes: Vec<u32>,
+}
+
+impl ThumbnailService {
+    pub(crate) fn new(pool: Pool<MySql>, storage: Arc<dyn Storage>, sizes: Vec<u32>) -> Self {
+        Self {
+            pool,
+            storage,
+            sizes,
+        }
+    }
+
+    // 在后台为图片生成缩略图，不阻塞上传请求
+    pub(crate) fn spawn_generate(self: &Arc<Self>, source_key: String, data: Bytes) {
+        if self.sizes.is_empty() {
+            return;
+        }
+        let service = Arc::clone(self);
+        tokio::spawn(async move {
+            if let Err(e) = service.generate(&source_key, data).await {
+                warn!("generate thumbnails for {} failed: {:?}", source_key, e);
+            }
+        });
+    }
+
+    // 在后台从存储读取图片生成缩略图，用于没有在内存中的大文件
+    pub(crate) fn spawn_generate_stored(self: &Arc<Self>, source_key: String) {
+        if self.sizes.is_empty() {
+            return;
+        }
+        let service = Arc::clone(self);
+        tokio::spawn(async move {
+            let res = match service.storage.get(&source_key).await {
+                Ok(Some(data)) => service.generate(&source_key, data).await,
+                Ok(None) => Ok(()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = res {
+                warn!("generate thumbnails for {} failed: {:?}", source_key, e);
+            }
+        });
+    }
+
+    async fn generate(&self, source_key: &str, data: Bytes) -> anyhow::Result<()> {
+        // 相同内容的图片之前已经生成过
+        if Thumbnail::exists_for_source(source_key, &self.pool).await? {
+            return Ok(());
+        }
+        // 解码和缩放比较耗CPU，放到阻塞线程池中执行
+        let sizes = self.sizes.clone();
+        let thumbnails =
+            tokio::task::spawn_blocking(move || thumbnail::generate(&data, &sizes)).await??;
+        for thumb in thumbnails {
+            let key = thumb.key_for(source_key);
+            let size = thumb.data.len() as i64;
+            self.storage
+                .put(&key, Bytes::from(thumb.data), thumb.mime_type)
+                .await?;
+            Thumbnail::create(
+                ThumbnailCreate {
+                    source_key: source_key.to_string(),
+                    max_size: thumb.max_size as i32,
+                    storage_key: key,
+                    size,
+                    mime_type: thumb.mime_type.to_string(),
+                    width: thumb.width as i32,
+                    height: thumb.height as i32,
+                },
+                &self.pool,
+            )
+            .await?;
+        }
+        info!("thumbnails generated for {}", source_key);
+        Ok(())
+    }
+
+    // 查询原图的缩略图，按原图key分组
+    pub(crate) async fn list_by_source_keys(
+        &self,
+        source_keys: &[&str],
+    ) -> Result<HashMap<String, Vec<Thumbnail>>, AppError> {
+        Ok(Thumbnail::list_by_source_keys(source_keys, &self.pool).await?)
+    }
+
+    // 根据缩略图的存储key查询
+    pub(crate) async fn find_by_storage_key(
+        &self,
+        storage_key: &str,
+    ) -> Result<Option<Thumbnail>, AppError> {
+        Ok(Thumbnail::find_by_storage_key(storage_key, &self.pool).await?)
+    }
+}
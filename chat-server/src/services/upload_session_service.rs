@@ -0,0 +1,275 @@
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use chrono::{Duration, Utc};
+use sqlx::{MySql, Pool};
+use tokio::fs;
+use tracing::{info, warn};
+
+use chat_core::models::user::CurUser;
+
+use crate::{
+    config::ChunkedUploadConfig,
+    error::AppError,
+    models::{
+        upload::{UploadCreate, UploadStatus, UploadedFile},
+        upload_session::{UploadSession, UploadSessionStatus},
+    },
+    services::message_service::MessageService,
+    storage::{
+        self,
+        chunk::{AssembledFile, ChunkStore},
+    },
+};
+
+// 每次清理的过期会话数量
+const CLEANUP_BATCH_SIZE: i64 = 100;
+
+#[derive(Debug)]
+pub(crate) struct UploadSessionService {
+    pub(crate) pool: Pool<MySql>,
+    pub(crate) message_service: Arc<MessageService>,
+    pub(crate) chunk_store: ChunkStore,
+    pub(crate) config: ChunkedUploadConfig,
+}
+
+impl UploadSessionService {
+    pub(crate) fn new(
+        pool: Pool<MySql>,
+        message_service: Arc<MessageService>,
+        config: ChunkedUploadConfig,
+    ) -> Self {
+        Self {
+            pool,
+            message_service,
+            chunk_store: ChunkStore::new(&config.dir),
+            config,
+        }
+    }
+
+    // 创建分片上传会话
+    pub(crate) async fn create(
+        &self,
+        user: &CurUser,
+        chat_id: i64,
+        filename: String,
+        mime_type: String,
+        size: u64,
+    ) -> Result<UploadSessionStatus, AppError> {
+        self.message_service
+            .check_upload(
+                user,
+                chat_id,
+                &filename,
+                &mime_type,
+                size,
+                self.config.max_file_size,
+            )
+            .await?;
+        let chunk_size = self.config.chunk_size.max(1);
+        let session = UploadSession {
+            id: uuid::Uuid::new_v4().to_string(),
+            ws_id: user.ws_id,
+            chat_id,
+            uploader_id: user.id,
+            filename,
+            mime_type,
+            size: size as i64,
+            chunk_size: chunk_size as i64,
+            chunk_count: size.div_ceil(chunk_size) as i32,
+            expires_at: self.expires_at(),
+            created_at: Utc::now(),
+        };
+        UploadSession::create(&session, &self.pool).await?;
+        Ok(status(session, vec![]))
+    }
+
+    // 上传一个分片
+    pub(crate) async fn put_chunk(
+        &self,
+        user: &CurUser,
+        session_id: &str,
+        index: i32,
+        data: Bytes,
+    ) -> Result<(), AppError> {
+        let session = self.find(user, session_id).await?;
+        let expected = session
+            .expected_chunk_size(index)
+            .ok_or_else(|| AppError::InvalidUpload("invalid chunk index".to_string()))?;
+        if data.len() as i64 != expected {
+            return Err(AppError::InvalidUpload(format!(
+                "chunk {} should be {} bytes",
+                index, expected
+            )));
+        }
+        let size = data.len() as i64;
+        self.chunk_store.put(session_id, index, data).await?;
+        UploadSession::add_chunk(session_id, index, size, &self.pool).await?;
+        UploadSession::touch(session_id, self.expires_at(), &self.pool).await?;
+        Ok(())
+    }
+
+    // 查询会话状态，客户端据此继续上传缺少的分片
+    pub(crate) async fn status(
+        &self,
+        user: &CurUser,
+        session_id: &str,
+    ) -> Result<UploadSessionStatus, AppError> {
+        let session = self.find(user, session_id).await?;
+        let received = UploadSession::list_chunks(session_id, &self.pool).await?;
+        Ok(status(session, received))
+    }
+
+    // 所有分片上传完成后合并成一个文件
+    // 合并和保存文件比较耗时，在事务外进行，只在最后锁住会话并记录上传信息
+    pub(crate) async fn finalize(
+        &self,
+        user: &CurUser,
+        session_id: &str,
+    ) -> Result<UploadedFile, AppError> {
+        let session = self.find(user, session_id).await?;
+        let received = UploadSession::list_chunks(session_id, &self.pool).await?;
+        if received.len() as i32 != session.chunk_count {
+            return Err(AppError::InvalidUpload(format!(
+                "{} of {} chunks received",
+                received.len(),
+                session.chunk_count
+            )));
+        }
+        let file = self
+            .chunk_store
+            .assemble(session_id, session.chunk_count)
+            .await?;
+        let res = self.store(user, session, &file).await;
+        if let Err(e) = fs::remove_file(&file.path).await {
+            warn!("remove assembled file {:?} failed: {:?}", file.path, e);
+        }
+        let (uploaded, has_dimensions) = res?;
+        if let Err(e) = self.chunk_store.remove(session_id).await {
+            warn!("remove chunks of session {} failed: {:?}", session_id, e);
+        }
+        // 能识别尺寸的图片在后台生成缩略图
+        if has_dimensions {
+            self.message_service.spawn_thumbnails(uploaded.key.clone());
+        }
+        Ok(uploaded)
+    }
+
+    // 保存合并后的文件，锁住会话后记录上传信息并删除会话，返回上传的文件和是否是能识别尺寸的图片
+    async fn store(
+        &self,
+        user: &CurUser,
+        session: UploadSession,
+        file: &AssembledFile,
+    ) -> Result<(UploadedFile, bool), AppError> {
+        if file.size as i64 != session.size {
+            return Err(AppError::InvalidUpload("file size mismatch".to_string()));
+        }
+        let key = storage::hash_key(user.ws_id, &file.hash, &session.filename);
+        let (width, height) = self
+            .message_service
+            .store_file(&key, &file.path, file.size, &session.mime_type)
+            .await?;
+        match self.record(user, session, key.clone(), width, height).await {
+            Ok(uploaded) => Ok((uploaded, width.is_some())),
+            Err(e) => {
+                // 会话已失效或超出配额时，删除刚保存的文件
+                self.message_service.discard_stored(&key).await;
+                Err(e)
+            }
+        }
+    }
+
+    // 删除会话并记录上传信息，会话已经被并发的合并或者清理删除时返回错误
+    async fn record(
+        &self,
+        user: &CurUser,
+        session: UploadSession,
+        key: String,
+        width: Option<i32>,
+        height: Option<i32>,
+    ) -> Result<UploadedFile, AppError> {
+        let mut tx = self.pool.begin().await?;
+        // 锁住会话，会话已经被并发的合并或者清理删除时不再重复记录
+        UploadSession::find_by_id(&session.id, true, &mut *tx)
+            .await?
+            .filter(|s| s.expires_at > Utc::now())
+            .ok_or(AppError::UploadSessionNotFound)?;
+        let uploaded = self
+            .message_service
+            .record_upload(
+                UploadCreate {
+                    ws_id: user.ws_id,
+                    chat_id: session.chat_id,
+                    uploader_id: user.id,
+                    storage_key: key,
+                    filename: session.filename,
+                    size: session.size,
+                    mime_type: session.mime_type,
+                    width,
+                    height,
+                    status: UploadStatus::Completed,
+                    expires_at: None,
+                },
+                &mut tx,
+            )
+            .await?;
+        UploadSession::delete(&session.id, &mut tx).await?;
+        tx.commit().await?;
+        Ok(uploaded)
+    }
+
+    // 清理过期未完成的会话，返回实际删除的会话数
+    pub(crate) async fn cleanup_expired(&self) -> Result<usize, AppError> {
+        let ids = UploadSession::list_expired(Utc::now(), CLEANUP_BATCH_SIZE, &self.pool).await?;
+        let mut deleted = 0;
+        for id in ids.iter() {
+            // 加锁后再次确认已经过期，避免和正在进行的合并冲突
+            let mut tx = self.pool.begin().await?;
+            let expired = UploadSession::find_by_id(id, true, &mut *tx)
+                .await?
+                .is_some_and(|s| s.expires_at < Utc::now());
+            if !expired {
+                continue;
+            }
+            UploadSession::delete(id, &mut tx).await?;
+            tx.commit().await?;
+            deleted += 1;
+            // 分片删除失败不影响其他会话的清理
+            if let Err(e) = self.chunk_store.remove(id).await {
+                warn!("remove chunks of session {} failed: {:?}", id, e);
+            }
+        }
+        if deleted > 0 {
+            info!("cleaned up {} expired upload sessions", deleted);
+        }
+        Ok(deleted)
+    }
+
+    async fn find(&self, user: &CurUser, session_id: &str) -> Result<UploadSession, AppError> {
+        if uuid::Uuid::try_parse(session_id).is_err() {
+            return Err(AppError::UploadSessionNotFound);
+        }
+        UploadSession::find_by_id(session_id, false, &self.pool)
+            .await?
+            .filter(|s| {
+                s.uploader_id == user.id && s.ws_id == user.ws_id && s.expires_at > Utc::now()
+            })
+            .ok_or(AppError::UploadSessionNotFound)
+    }
+
+    fn expires_at(&self) -> chrono::DateTime<Utc> {
+        Utc::now() + Duration::seconds(self.config.session_ttl as i64)
+    }
+}
+
+fn status(session: UploadSession, received_chunks: Vec<i32>) -> UploadSessionStatus {
+    UploadSessionStatus {
+        session_id: session.id,
+        size: session.size,
+        chunk_size: session.chunk_size,
+        chunk_count: session.chunk_count,
+        received_chunks,
+        expires_at: session.expires_at,
+    }
+}
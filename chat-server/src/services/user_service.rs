@@ -51,11 +51,17 @@ impl UserService {
         Ok(user_id)
     }
 
-    // 校验用户ID列表的合法性
-    pub async fn validate_user_ids(&self, user_ids: &Vec<i64>) -> Result<(), AppError> {
+    // 根据用户ID批量查询用户
+    pub async fn list_by_ids(&self, user_ids: &[i64]) -> Result<Vec<User>, AppError> {
+        let users = User::list_by_ids(user_ids, &self.pool).await?;
+        Ok(users)
+    }
+
+    // 校验用户ID列表的合法性，其他空间的用户视为不存在
+    pub async fn validate_user_ids(&self, user_ids: &Vec<i64>, ws_id: i64) -> Result<(), AppError> {
         for user_id in user_ids {
             let user = User::find_by_id(*user_id, &self.pool).await?;
-            if user.is_none() {
+            if user.is_none_or(|u| u.ws_id != ws_id) {
                 return Err(AppError::UserNotFound);
             }
         }
@@ -3,17 +3,32 @@ use sqlx::{MySql, Pool};
 use std::sync::Arc;
 use tracing::info;
 
-use crate::{error::AppError, models::workspace::Workspace, services::user_service::UserService};
+use chat_core::models::user::CurUser;
+
+use crate::{
+    error::AppError,
+    models::{role::WorkspaceRole, user::User, workspace::Workspace},
+    services::{authz_service::AuthzService, user_service::UserService},
+};
 
 #[derive(Debug)]
 pub(crate) struct WorkspaceService {
     pub(crate) pool: Pool<MySql>,
     pub(crate) user_service: Arc<UserService>,
+    pub(crate) authz_service: Arc<AuthzService>,
 }
 
 impl WorkspaceService {
-    pub fn new(pool: Pool<MySql>, user_service: Arc<UserService>) -> Self {
-        Self { pool, user_service }
+    pub fn new(
+        pool: Pool<MySql>,
+        user_service: Arc<UserService>,
+        authz_service: Arc<AuthzService>,
+    ) -> Self {
+        Self {
+            pool,
+            user_service,
+            authz_service,
+        }
     }
 
     // 创建工作空间
@@ -41,24 +56,81 @@ impl WorkspaceService {
         Ok(Workspace::find_by_name(name, &self.pool).await?)
     }
 
-    // 变更工作空间拥有者
-    pub async fn change_owner(&self, id: i64, owner_id: i64) -> Result<bool, AppError> {
-        // 校验工作空间ID合法性
+    // 变更工作空间拥有者，只有当前拥有者可以转让
+    pub async fn change_owner(
+        &self,
+        user: &CurUser,
+        id: i64,
+        owner_id: i64,
+    ) -> Result<bool, AppError> {
+        // 校验工作空间ID合法性，只能操作自己所在的空间
+        if id != user.ws_id {
+            return Err(AppError::WorkspaceNotFound);
+        }
         let workspace = self
             .find_by_id(id)
             .await?
             .ok_or(AppError::WorkspaceNotFound)?;
+        self.authz_service
+            .require_workspace_role(id, user, WorkspaceRole::Owner)
+            .await?;
         if workspace.owner_id == owner_id {
             info!("工作空间拥有者未改变");
             return Ok(true);
         }
-        // 校验用户ID合法性
+        // 校验用户ID合法性，新的拥有者必须属于该空间
         self.user_service
             .find_by_id(owner_id)
             .await?
+            .filter(|u| u.ws_id == id)
+            .ok_or(AppError::UserNotFound)?;
+
+        let mut tx = self.pool.begin().await?;
+        let res = Workspace::change_owner(id, owner_id, &mut *tx).await?;
+        // 原拥有者降为管理员
+        User::update_ws_role(workspace.owner_id, WorkspaceRole::Admin, &mut *tx).await?;
+        tx.commit().await?;
+        Ok(res)
+    }
+
+    // 设置用户在空间中的角色，管理员只能设置比自己低的角色
+    pub async fn set_role(
+        &self,
+        user: &CurUser,
+        user_id: i64,
+        role: WorkspaceRole,
+    ) -> Result<bool, AppError> {
+        let operator_role = self
+            .authz_service
+            .require_workspace_role(user.ws_id, user, WorkspaceRole::Admin)
+            .await?;
+        // 拥有者只能通过转让产生
+        if role == WorkspaceRole::Owner || user_id == user.id {
+            return Err(AppError::PermissionDenied);
+        }
+        let target_role = self
+            .authz_service
+            .workspace_role(user.ws_id, user_id)
+            .await?
             .ok_or(AppError::UserNotFound)?;
+        if target_role >= operator_role || role >= operator_role {
+            return Err(AppError::PermissionDenied);
+        }
+        Ok(User::update_ws_role(user_id, role, &self.pool).await? > 0)
+    }
 
-        Ok(Workspace::change_owner(id, owner_id, &self.pool).await?)
+    // 设置空间的附件保留天数，None 表示使用默认值，0 表示永久保留
+    pub async fn set_retention(
+        &self,
+        user: &CurUser,
+        retention_days: Option<u32>,
+    ) -> Result<(), AppError> {
+        self.authz_service
+            .require_workspace_role(user.ws_id, user, WorkspaceRole::Admin)
+            .await?;
+        let retention_days = retention_days.map(|days| days.min(i32::MAX as u32) as i32);
+        Workspace::set_retention_days(user.ws_id, retention_days, &self.pool).await?;
+        Ok(())
     }
 
     // 根据ID查询工作空间
@@ -0,0 +1,129 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use axum::body::Bytes;
+use sha2::{Digest, Sha256};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+
+// 合并时每次读取的字节数
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+// 合并后的文件，hash 是内容的 SHA256（十六进制）
+#[derive(Debug)]
+pub(crate) struct AssembledFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub hash: String,
+}
+
+// 分片上传的临时分片，保存在本地目录，合并后删除
+#[derive(Debug)]
+pub(crate) struct ChunkStore {
+    root: PathBuf,
+}
+
+impl ChunkStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn dir(&self, session_id: &str) -> Result<PathBuf> {
+        if uuid::Uuid::try_parse(session_id).is_err() {
+            bail!("invalid upload session id: {}", session_id);
+        }
+        Ok(self.root.join(session_id))
+    }
+
+    // 保存分片，先写临时文件再重命名，重复上传时覆盖
+    pub(crate) async fn put(&self, session_id: &str, index: i32, data: Bytes) -> Result<()> {
+        let dir = self.dir(session_id)?;
+        fs::create_dir_all(&dir).await?;
+        let tmp = dir.join(format!("{}.tmp-{}", index, uuid::Uuid::now_v7()));
+        fs::write(&tmp, &data).await?;
+        fs::rename(&tmp, dir.join(index.to_string())).await?;
+        Ok(())
+    }
+
+    // 按顺序把所有分片合并到会话目录下的一个新文件，边写边计算内容hash，不把文件读入内存
+    // 并发合并时各自写不同的文件，互不影响
+    pub(crate) async fn assemble(
+        &self,
+        session_id: &str,
+        chunk_count: i32,
+    ) -> Result<AssembledFile> {
+        let dir = self.dir(session_id)?;
+        let path = dir.join(format!("assembled-{}", uuid::Uuid::now_v7()));
+        match copy_chunks(&dir, chunk_count, &path).await {
+            Ok((size, hash)) => Ok(AssembledFile { path, size, hash }),
+            Err(e) => {
+                let _ = fs::remove_file(&path).await;
+                Err(e)
+            }
+        }
+    }
+
+    // 删除会话的所有分片
+    pub(crate) async fn remove(&self, session_id: &str) -> Result<()> {
+        match fs::remove_dir_all(self.dir(session_id)?).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+// 把分片依次写入 path，返回文件大小和内容hash
+async fn copy_chunks(dir: &Path, chunk_count: i32, path: &Path) -> Result<(u64, String)> {
+    let mut file = fs::File::create(path).await?;
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+    let mut buf = vec![0u8; COPY_BUFFER_SIZE];
+    for index in 0..chunk_count {
+        let mut chunk = fs::File::open(dir.join(index.to_string())).await?;
+        loop {
+            let n = chunk.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            file.write_all(&buf[..n]).await?;
+            size += n as u64;
+        }
+    }
+    file.flush().await?;
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_chunk_store() {
+        let root = std::env::temp_dir().join(format!("chat-chunks-{}", uuid::Uuid::now_v7()));
+        let store = ChunkStore::new(&root);
+        let session_id = uuid::Uuid::new_v4().to_string();
+
+        store
+            .put(&session_id, 1, Bytes::from_static(b"world"))
+            .await
+            .unwrap();
+        store
+            .put(&session_id, 0, Bytes::from_static(b"hello "))
+            .await
+            .unwrap();
+        let file = store.assemble(&session_id, 2).await.unwrap();
+        assert_eq!(&fs::read(&file.path).await.unwrap()[..], b"hello world");
+        assert_eq!(file.size, 11);
+        assert_eq!(file.hash, format!("{:x}", Sha256::digest(b"hello world")));
+        assert!(store.put("../etc", 0, Bytes::new()).await.is_err());
+
+        store.remove(&session_id).await.unwrap();
+        assert!(store.assemble(&session_id, 2).await.is_err());
+        store.remove(&session_id).await.unwrap();
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use axum::body::Bytes;
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt, SeekFrom},
+};
+
+use crate::storage::{is_valid_key, FileReader, RangeReader, Storage};
+
+// 本地文件系统存储
+#[derive(Debug)]
+pub(crate) struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        if !is_valid_key(key) {
+            bail!("invalid storage key: {}", key);
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, data: Bytes, _content_type: &str) -> Result<()> {
+        let path = self.path(key)?;
+        if fs::try_exists(&path).await? {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        // 先写临时文件再重命名，避免读到写了一半的文件
+        let tmp = path.with_extension(format!("tmp-{}", uuid::Uuid::now_v7()));
+        fs::write(&tmp, &data).await?;
+        fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn put_reader(
+        &self,
+        key: &str,
+        mut reader: FileReader,
+        _size: u64,
+        _content_type: &str,
+    ) -> Result<()> {
+        let path = self.path(key)?;
+        if fs::try_exists(&path).await? {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let tmp = path.with_extension(format!("tmp-{}", uuid::Uuid::now_v7()));
+        let mut file = fs::File::create(&tmp).await?;
+        tokio::io::copy(&mut reader, &mut file).await?;
+        file.sync_all().await?;
+        fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>> {
+        match fs::read(self.path(key)?).await {
+            Ok(data) => Ok(Some(Bytes::from(data))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn open_range(&self, key: &str, start: u64, len: u64) -> Result<Option<RangeReader>> {
+        let mut file = match fs::File::open(self.path(key)?).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        file.seek(SeekFrom::Start(start)).await?;
+        Ok(Some(Box::pin(file.take(len))))
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<u64>> {
+        match fs::metadata(self.path(key)?).await {
+            Ok(meta) => Ok(Some(meta.len())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<()> {
+        let (from, to) = (self.path(from)?, self.path(to)?);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let tmp = to.with_extension(format!("tmp-{}", uuid::Uuid::now_v7()));
+        fs::copy(&from, &tmp).await?;
+        fs::rename(&tmp, &to).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match fs::remove_file(self.path(key)?).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_storage() {
+        let root = std::env::temp_dir().join(format!("chat-storage-{}", uuid::Uuid::now_v7()));
+        let storage = LocalStorage::new(&root);
+
+        let key = "1/ab/abc.txt";
+        assert!(storage.get(key).await.unwrap().is_none());
+        storage
+            .put(key, Bytes::from_static(b"hello"), "text/plain")
+            .await
+            .unwrap();
+        assert_eq!(&storage.get(key).await.unwrap().unwrap()[..], b"hello");
+        assert_eq!(storage.size(key).await.unwrap(), Some(5));
+        storage
+            .put_reader("1/ef/efg.txt", Box::new(&b"streamed"[..]), 8, "text/plain")
+            .await
+            .unwrap();
+        assert_eq!(
+            &storage.get("1/ef/efg.txt").await.unwrap().unwrap()[..],
+            b"streamed"
+        );
+        let mut data = vec![];
+        let mut reader = storage.open_range(key, 1, 3).await.unwrap().unwrap();
+        reader.read_to_end(&mut data).await.unwrap();
+        assert_eq!(&data[..], b"ell");
+        assert!(storage
+            .open_range("1/no/none.txt", 0, 1)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(storage.get("../abc.txt").await.is_err());
+
+        storage.copy(key, "1/cd/cde.txt").await.unwrap();
+        assert_eq!(
+            &storage.get("1/cd/cde.txt").await.unwrap().unwrap()[..],
+            b"hello"
+        );
+
+        storage.delete(key).await.unwrap();
+        assert!(storage.get(key).await.unwrap().is_none());
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
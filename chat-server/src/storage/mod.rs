@@ -0,0 +1,231 @@
+use std::{fmt::Debug, pin::Pin, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use axum::body::Bytes;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncRead;
+
+use crate::{
+    config::{StorageBackend, StorageConfig},
+    storage::{local::LocalStorage, s3::S3Storage},
+};
+
+pub(crate) mod chunk;
+pub(crate) mod local;
+pub(crate) mod s3;
+pub(crate) mod thumbnail;
+
+// 流式保存文件时的数据来源
+pub(crate) type FileReader = Box<dyn AsyncRead + Send + Sync + Unpin>;
+
+// 流式读取文件的一段，下载时直接作为响应体
+pub(crate) type RangeReader = Pin<Box<dyn AsyncRead + Send>>;
+
+// 文件存储后端，key 由 content_key 按内容生成
+#[async_trait]
+pub(crate) trait Storage: Debug + Send + Sync {
+    // 保存文件，同一个key重复保存时内容相同，可以直接跳过
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> Result<()>;
+
+    // 流式保存文件，大文件不需要全部读入内存，size 是文件大小
+    async fn put_reader(
+        &self,
+        key: &str,
+        reader: FileReader,
+        size: u64,
+        content_type: &str,
+    ) -> Result<()>;
+
+    // 读取文件，不存在时返回None
+    async fn get(&self, key: &str) -> Result<Option<Bytes>>;
+
+    // 打开文件从 start 开始的 len 个字节，用于下载和 Range 请求，不存在时返回None
+    async fn open_range(&self, key: &str, start: u64, len: u64) -> Result<Option<RangeReader>>;
+
+    // 文件大小，不存在时返回None
+    async fn size(&self, key: &str) -> Result<Option<u64>>;
+
+    // 删除文件，不存在时忽略
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    // 复制文件，目标已存在时覆盖
+    async fn copy(&self, from: &str, to: &str) -> Result<()>;
+
+    // 生成下载的预签名地址，客户端直接从存储下载，不支持时返回None
+    async fn presign_get(
+        &self,
+        _key: &str,
+        _content_type: &str,
+        _content_disposition: &str,
+        _expires_in: Duration,
+    ) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    // 生成上传的预签名地址，客户端直接上传到存储，不支持时返回None
+    // 签名包含文件类型和大小，客户端只能上传申请时声明的文件
+    async fn presign_put(
+        &self,
+        _key: &str,
+        _content_type: &str,
+        _size: u64,
+        _expires_in: Duration,
+    ) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+// 根据配置创建存储后端
+pub(crate) fn from_config(config: &StorageConfig) -> Result<Arc<dyn Storage>> {
+    match config.backend {
+        StorageBackend::Local => {
+            let local = config
+                .local
+                .as_ref()
+                .context("storage.local is required for local backend")?;
+            Ok(Arc::new(LocalStorage::new(&local.root)))
+        }
+        StorageBackend::S3 => {
+            let s3 = config
+                .s3
+                .as_ref()
+                .context("storage.s3 is required for s3 backend")?;
+            Ok(Arc::new(S3Storage::new(s3)))
+        }
+    }
+}
+
+// 存储key对应的下载地址，base_url 为配置的 server.file_base_url
+pub(crate) fn file_url(base_url: &str, key: &str) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), key)
+}
+
+// 从下载地址中解析出存储key，只接受 base_url 下的地址
+pub(crate) fn key_from_url<'a>(base_url: &str, url: &'a str) -> Option<&'a str> {
+    url.strip_prefix(base_url.trim_end_matches('/'))
+        .and_then(|rest| rest.strip_prefix('/'))
+        .filter(|key| is_valid_key(key))
+}
+
+// 客户端直传的文件无法预先知道内容hash，使用随机key：{ws_id}/{id前两位}/{id}.{扩展名}
+pub(crate) fn random_key(ws_id: i64, filename: &str) -> String {
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    match extension(filename) {
+        Some(ext) => format!("{}/{}/{}.{}", ws_id, &id[..2], id, ext),
+        None => format!("{}/{}/{}", ws_id, &id[..2], id),
+    }
+}
+
+// key 中的文件名就是内容hash，直接作为 ETag
+pub(crate) fn etag_for_key(key: &str) -> String {
+    let name = key.rsplit('/').next().unwrap_or(key);
+    let hash = name.split('.').next().unwrap_or(name);
+    format!("\"{}\"", hash)
+}
+
+// 按内容生成存储key：{ws_id}/{hash前两位}/{hash}.{扩展名}
+// key 中带上空间ID，不同空间的文件互不影响，便于按空间统计和清理
+pub(crate) fn content_key(ws_id: i64, data: &[u8], filename: &str) -> String {
+    hash_key(ws_id, &format!("{:x}", Sha256::digest(data)), filename)
+}
+
+// 按已经计算好的内容hash（十六进制）生成存储key，用于边读边计算hash的大文件
+pub(crate) fn hash_key(ws_id: i64, hash: &str, filename: &str) -> String {
+    match extension(filename) {
+        Some(ext) => format!("{}/{}/{}.{}", ws_id, &hash[..2], hash, ext),
+        None => format!("{}/{}/{}", ws_id, &hash[..2], hash),
+    }
+}
+
+// 文件扩展名，只保留小写的字母数字
+fn extension(filename: &str) -> Option<String> {
+    filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .filter(|ext| {
+            !ext.is_empty() && ext.len() <= 10 && ext.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+// 校验key的合法性，防止路径穿越
+pub(crate) fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.split('/').all(|part| {
+            !part.is_empty()
+                && part != "."
+                && part != ".."
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_key() {
+        let key = content_key(1, b"hello", "Hello.PNG");
+        assert!(key.starts_with("1/2c/2cf24dba"));
+        assert!(key.ends_with(".png"));
+        assert_eq!(key, content_key(1, b"hello", "other.png"));
+        assert_ne!(key, content_key(2, b"hello", "Hello.PNG"));
+        assert!(!content_key(1, b"hello", "noext").contains('.'));
+        assert_eq!(
+            key,
+            hash_key(1, &format!("{:x}", Sha256::digest(b"hello")), "a.png")
+        );
+        assert!(!content_key(1, b"hello", "a.../../x").contains(".."));
+        assert!(is_valid_key(&key));
+        assert_eq!(
+            etag_for_key(&key),
+            "\"2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824\""
+        );
+    }
+
+    #[test]
+    fn test_random_key() {
+        let key = random_key(1, "a.JPG");
+        assert!(key.starts_with("1/"));
+        assert!(key.ends_with(".jpg"));
+        assert!(is_valid_key(&key));
+        assert_ne!(key, random_key(1, "a.JPG"));
+    }
+
+    #[test]
+    fn test_file_url() {
+        let base_url = "http://localhost:8888/api/files/";
+        let url = file_url(base_url, "1/ab/abcdef.jpg");
+        assert_eq!(url, "http://localhost:8888/api/files/1/ab/abcdef.jpg");
+        assert_eq!(key_from_url(base_url, &url), Some("1/ab/abcdef.jpg"));
+        // 配置的地址末尾有没有斜杠都可以
+        let url = file_url("https://cdn.example.com/files", "1/ab/abcdef.jpg");
+        assert_eq!(url, "https://cdn.example.com/files/1/ab/abcdef.jpg");
+        assert_eq!(
+            key_from_url("https://cdn.example.com/files/", &url),
+            Some("1/ab/abcdef.jpg")
+        );
+        assert_eq!(key_from_url(base_url, "/api/files/1/ab/abcdef.jpg"), None);
+        assert_eq!(
+            key_from_url(base_url, "http://localhost:8888/api/files/../x"),
+            None
+        );
+        assert_eq!(
+            key_from_url(base_url, "http://localhost:8888/api/filesx/1/ab/abcdef.jpg"),
+            None
+        );
+        assert_eq!(key_from_url(base_url, "http://example.com/a.jpg"), None);
+    }
+
+    #[test]
+    fn test_is_valid_key() {
+        assert!(is_valid_key("1/ab/abcdef.jpg"));
+        assert!(!is_valid_key(""));
+        assert!(!is_valid_key("/etc/passwd"));
+        assert!(!is_valid_key("1/../../etc/passwd"));
+        assert!(!is_valid_key("1//a"));
+        assert!(!is_valid_key("1/a b"));
+    }
+}
@@ -0,0 +1,267 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use aws_sdk_s3::{
+    config::{BehaviorVersion, Credentials, Region},
+    presigning::PresigningConfig,
+    primitives::ByteStream,
+    Client,
+};
+use axum::body::Bytes;
+use http_body::Frame;
+use http_body_util::StreamBody;
+use tokio_stream::StreamExt;
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    config::S3StorageConfig,
+    storage::{FileReader, RangeReader, Storage},
+};
+
+// S3兼容的对象存储，也可以用于 MinIO 等自建服务
+#[derive(Debug)]
+pub(crate) struct S3Storage {
+    client: Client,
+    bucket: String,
+}
+
+impl S3Storage {
+    pub fn new(config: &S3StorageConfig) -> Self {
+        let credentials = Credentials::new(
+            &config.access_key,
+            &config.secret_key,
+            None,
+            None,
+            "chat-server",
+        );
+        let mut builder = aws_sdk_s3::config::Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(config.region.clone()))
+            .credentials_provider(credentials)
+            .force_path_style(config.force_path_style);
+        if let Some(endpoint) = &config.endpoint {
+            builder = builder.endpoint_url(endpoint);
+        }
+        Self {
+            client: Client::from_conf(builder.build()),
+            bucket: config.bucket.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> Result<()> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(data))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn put_reader(
+        &self,
+        key: &str,
+        reader: FileReader,
+        size: u64,
+        content_type: &str,
+    ) -> Result<()> {
+        let body = StreamBody::new(ReaderStream::new(reader).map(|r| r.map(Frame::data)));
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .content_length(size as i64)
+            .body(ByteStream::from_body_1_x(body))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>> {
+        match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(output) => Ok(Some(output.body.collect().await?.into_bytes())),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn open_range(&self, key: &str, start: u64, len: u64) -> Result<Option<RangeReader>> {
+        if len == 0 {
+            return Ok(Some(Box::pin(tokio::io::empty())));
+        }
+        match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .range(format!("bytes={}-{}", start, start + len - 1))
+            .send()
+            .await
+        {
+            Ok(output) => Ok(Some(Box::pin(output.body.into_async_read()))),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<u64>> {
+        match self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(output) => Ok(Some(output.content_length.unwrap_or_default() as u64)),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<()> {
+        self.client
+            .copy_object()
+            .bucket(&self.bucket)
+            .copy_source(format!("{}/{}", self.bucket, from))
+            .key(to)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn presign_get(
+        &self,
+        key: &str,
+        content_type: &str,
+        content_disposition: &str,
+        expires_in: Duration,
+    ) -> Result<Option<String>> {
+        let req = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .response_content_type(content_type)
+            .response_content_disposition(content_disposition)
+            .presigned(PresigningConfig::expires_in(expires_in)?)
+            .await?;
+        Ok(Some(req.uri().to_string()))
+    }
+
+    async fn presign_put(
+        &self,
+        key: &str,
+        content_type: &str,
+        size: u64,
+        expires_in: Duration,
+    ) -> Result<Option<String>> {
+        let req = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .content_length(size as i64)
+            .presigned(PresigningConfig::expires_in(expires_in)?)
+            .await?;
+        Ok(Some(req.uri().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppConfig;
+    use tokio::io::AsyncReadExt;
+
+    fn s3_config() -> S3StorageConfig {
+        AppConfig::load().unwrap().storage.s3.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_s3_presign() {
+        let storage = S3Storage::new(&s3_config());
+        let url = storage
+            .presign_put("1/ab/abc.png", "image/png", 1024, Duration::from_secs(60))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(url.starts_with("http://localhost:9000/chat/1/ab/abc.png?"));
+        assert!(url.contains("X-Amz-Signature="));
+        assert!(url.contains("content-length"));
+        assert!(url.contains("X-Amz-Expires=60"));
+
+        let url = storage
+            .presign_get(
+                "1/ab/abc.png",
+                "image/png",
+                "inline",
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(url.contains("response-content-disposition=inline"));
+    }
+
+    // 需要本地启动 MinIO：docker run -p 9000:9000 minio/minio server /data
+    // 并创建 chat 存储桶
+    #[tokio::test]
+    async fn test_s3_storage() {
+        let storage = S3Storage::new(&s3_config());
+        let key = format!("test/{}.txt", uuid::Uuid::now_v7());
+        assert!(storage.get(&key).await.unwrap().is_none());
+        assert!(storage.size(&key).await.unwrap().is_none());
+        storage
+            .put(&key, Bytes::from_static(b"hello"), "text/plain")
+            .await
+            .unwrap();
+        assert_eq!(storage.size(&key).await.unwrap(), Some(5));
+        let streamed = format!("test/{}.txt", uuid::Uuid::now_v7());
+        storage
+            .put_reader(&streamed, Box::new(&b"streamed"[..]), 8, "text/plain")
+            .await
+            .unwrap();
+        assert_eq!(
+            &storage.get(&streamed).await.unwrap().unwrap()[..],
+            b"streamed"
+        );
+        storage.delete(&streamed).await.unwrap();
+        assert_eq!(&storage.get(&key).await.unwrap().unwrap()[..], b"hello");
+        let mut data = vec![];
+        let mut reader = storage.open_range(&key, 1, 3).await.unwrap().unwrap();
+        reader.read_to_end(&mut data).await.unwrap();
+        assert_eq!(&data[..], b"ell");
+        let copied = format!("test/{}.txt", uuid::Uuid::now_v7());
+        storage.copy(&key, &copied).await.unwrap();
+        assert_eq!(&storage.get(&copied).await.unwrap().unwrap()[..], b"hello");
+        storage.delete(&copied).await.unwrap();
+        storage.delete(&key).await.unwrap();
+        assert!(storage.get(&key).await.unwrap().is_none());
+    }
+}
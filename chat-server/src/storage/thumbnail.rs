@@ -0,0 +1,112 @@
+use std::io::Cursor;
+
+use anyhow::Result;
+use image::{codecs::jpeg::JpegEncoder, DynamicImage, ImageFormat, ImageReader, Limits};
+
+// 原图的最大边长，超过时不生成缩略图，避免解码超大图片占满内存
+const MAX_SOURCE_DIMENSION: u32 = 12000;
+const JPEG_QUALITY: u8 = 80;
+
+// 生成的缩略图
+#[derive(Debug, Clone)]
+pub(crate) struct ThumbnailImage {
+    pub max_size: u32,
+    pub data: Vec<u8>,
+    pub mime_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ThumbnailImage {
+    fn extension(&self) -> &'static str {
+        if self.mime_type == "image/png" {
+            "png"
+        } else {
+            "jpg"
+        }
+    }
+
+    // 缩略图保存在原图旁边：{原图key去掉扩展名}_{最长边}.{扩展名}
+    pub(crate) fn key_for(&self, source_key: &str) -> String {
+        let (dir, name) = source_key.rsplit_once('/').unwrap_or(("", source_key));
+        let stem = name.split('.').next().unwrap_or(name);
+        let file = format!("{}_{}.{}", stem, self.max_size, self.extension());
+        if dir.is_empty() {
+            file
+        } else {
+            format!("{}/{}", dir, file)
+        }
+    }
+}
+
+// 按给定的最长边生成缩略图，原图比目标尺寸小时跳过
+// 有透明通道的图片保存为PNG，其他保存为JPEG
+pub(crate) fn generate(data: &[u8], sizes: &[u32]) -> Result<Vec<ThumbnailImage>> {
+    let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    reader.limits(limits);
+    let image = reader.decode()?;
+
+    let mut thumbnails = Vec::new();
+    for &max_size in sizes {
+        if max_size == 0 || image.width().max(image.height()) <= max_size {
+            continue;
+        }
+        let thumb = image.thumbnail(max_size, max_size);
+        let (data, mime_type) = encode(&thumb)?;
+        thumbnails.push(ThumbnailImage {
+            max_size,
+            data,
+            mime_type,
+            width: thumb.width(),
+            height: thumb.height(),
+        });
+    }
+    Ok(thumbnails)
+}
+
+fn encode(image: &DynamicImage) -> Result<(Vec<u8>, &'static str)> {
+    let mut buf = Vec::new();
+    if image.color().has_alpha() {
+        image.write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)?;
+        Ok((buf, "image/png"))
+    } else {
+        JpegEncoder::new_with_quality(&mut buf, JPEG_QUALITY).encode_image(&image.to_rgb8())?;
+        Ok((buf, "image/jpeg"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{RgbImage, RgbaImage};
+
+    fn png(image: DynamicImage) -> Vec<u8> {
+        let mut buf = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_generate_thumbnails() {
+        let data = png(DynamicImage::ImageRgb8(RgbImage::new(1000, 500)));
+        let thumbs = generate(&data, &[320, 960, 2000]).unwrap();
+        assert_eq!(thumbs.len(), 2);
+        assert_eq!((thumbs[0].width, thumbs[0].height), (320, 160));
+        assert_eq!(thumbs[0].mime_type, "image/jpeg");
+        assert_eq!((thumbs[1].width, thumbs[1].height), (960, 480));
+        assert_eq!(thumbs[0].key_for("1/ab/abc.png"), "1/ab/abc_320.jpg");
+
+        let data = png(DynamicImage::ImageRgba8(RgbaImage::new(400, 800)));
+        let thumbs = generate(&data, &[320]).unwrap();
+        assert_eq!((thumbs[0].width, thumbs[0].height), (160, 320));
+        assert_eq!(thumbs[0].mime_type, "image/png");
+        assert_eq!(thumbs[0].key_for("1/ab/abc"), "1/ab/abc_320.png");
+
+        assert!(generate(b"not an image", &[320]).is_err());
+    }
+}
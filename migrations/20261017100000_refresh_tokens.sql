@@ -0,0 +1,14 @@
+-- 刷新令牌表，令牌本身只返回给客户端，数据库中只保存sha256
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id bigint PRIMARY KEY AUTO_INCREMENT not null comment '主键ID',
+    user_id bigint NOT NULL comment '用户ID',
+    family_id VARCHAR(64) NOT NULL comment '令牌族ID，同一次登录轮换出的令牌属于同一族',
+    token_hash VARCHAR(64) NOT NULL comment '令牌sha256',
+    expires_at DATETIME NOT NULL comment '过期时间',
+    used_at DATETIME comment '轮换时间，已轮换的令牌再次使用视为重放',
+    revoked_at DATETIME comment '吊销时间',
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP() comment '创建时间'
+) comment '刷新令牌表';
+
+create unique index idx_refresh_tokens_token_hash on refresh_tokens (token_hash);
+create index idx_refresh_tokens_family_id on refresh_tokens (family_id);
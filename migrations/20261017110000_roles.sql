@@ -0,0 +1,11 @@
+-- 空间角色，空间拥有者由 workspaces.owner_id 决定，这里只保存 admin member guest
+ALTER TABLE users ADD COLUMN ws_role VARCHAR(20) NOT NULL DEFAULT 'member' comment '空间角色 admin member guest';
+
+-- 聊天室角色
+ALTER TABLE chat_members ADD COLUMN role VARCHAR(20) NOT NULL DEFAULT 'member' comment '聊天角色 owner admin member';
+
+-- 已有的聊天室，将最早加入的成员设置为拥有者
+UPDATE chat_members cm
+    INNER JOIN (SELECT chat_id, MIN(id) AS id FROM chat_members GROUP BY chat_id) first_member
+    ON cm.id = first_member.id
+SET cm.role = 'owner';
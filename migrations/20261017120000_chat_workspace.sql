@@ -0,0 +1,11 @@
+-- 聊天室归属于空间
+ALTER TABLE chats ADD COLUMN ws_id bigint NOT NULL DEFAULT 0 comment '空间ID';
+
+-- 已有的聊天室，按成员所在的空间回填
+UPDATE chats c
+SET c.ws_id = COALESCE(
+    (SELECT MIN(u.ws_id) FROM chat_members cm INNER JOIN users u ON u.id = cm.user_id WHERE cm.chat_id = c.id),
+    0
+);
+
+create index idx_chats_ws_id on chats (ws_id);
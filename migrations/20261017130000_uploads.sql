@@ -0,0 +1,15 @@
+-- 上传文件表，记录上传者和所属聊天室，只有该聊天室的消息可以引用
+CREATE TABLE IF NOT EXISTS uploads (
+    id bigint PRIMARY KEY AUTO_INCREMENT not null comment '主键ID',
+    ws_id bigint NOT NULL comment '空间ID',
+    chat_id bigint NOT NULL comment '聊天ID',
+    uploader_id bigint NOT NULL comment '上传者ID',
+    storage_key VARCHAR(255) NOT NULL comment '存储key，按内容hash生成',
+    filename VARCHAR(255) NOT NULL comment '原始文件名',
+    size bigint NOT NULL comment '文件大小（字节）',
+    mime_type VARCHAR(100) NOT NULL comment '文件类型',
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP() comment '创建时间'
+) comment '上传文件表';
+
+create index idx_uploads_chat_id_storage_key on uploads (chat_id, storage_key);
+create index idx_uploads_storage_key on uploads (storage_key);
@@ -0,0 +1,56 @@
+-- 上传文件记录图片尺寸
+ALTER TABLE uploads
+    ADD COLUMN width int NULL comment '图片宽度' AFTER mime_type,
+    ADD COLUMN height int NULL comment '图片高度' AFTER width;
+
+-- 消息附件表，代替 messages.files 中逗号拼接的文件列表
+CREATE TABLE IF NOT EXISTS attachments (
+    id bigint PRIMARY KEY AUTO_INCREMENT not null comment '主键ID',
+    message_id bigint NOT NULL comment '消息ID',
+    chat_id bigint NOT NULL comment '聊天ID',
+    storage_key VARCHAR(255) NOT NULL comment '存储key',
+    filename VARCHAR(255) NOT NULL comment '原始文件名',
+    size bigint NOT NULL comment '文件大小（字节）',
+    mime_type VARCHAR(100) NOT NULL comment '文件类型',
+    width int NULL comment '图片宽度',
+    height int NULL comment '图片高度',
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP() comment '创建时间'
+) comment '消息附件表';
+
+create index idx_attachments_message_id on attachments (message_id);
+create index idx_attachments_storage_key_chat_id on attachments (storage_key, chat_id);
+
+-- 迁移已有消息的文件列表，能找到上传记录的使用上传记录中的信息
+-- 绝对地址和相对地址都去掉 /api/files/ 前缀，超出 storage_key 长度的文件无法迁移，直接跳过
+INSERT INTO attachments (message_id, chat_id, storage_key, filename, size, mime_type, created_at)
+SELECT t.id,
+       t.chat_id,
+       t.storage_key,
+       COALESCE(
+           (SELECT u.filename FROM uploads u
+            WHERE u.chat_id = t.chat_id AND u.storage_key = t.storage_key ORDER BY u.id LIMIT 1),
+           SUBSTRING_INDEX(t.storage_key, '/', -1)),
+       COALESCE(
+           (SELECT u.size FROM uploads u
+            WHERE u.chat_id = t.chat_id AND u.storage_key = t.storage_key ORDER BY u.id LIMIT 1),
+           0),
+       COALESCE(
+           (SELECT u.mime_type FROM uploads u
+            WHERE u.chat_id = t.chat_id AND u.storage_key = t.storage_key ORDER BY u.id LIMIT 1),
+           'application/octet-stream'),
+       t.created_at
+FROM (
+    SELECT m.id,
+           m.chat_id,
+           m.created_at,
+           SUBSTRING_INDEX(f.url, '/api/files/', -1) AS storage_key
+    FROM messages m
+    JOIN JSON_TABLE(
+        CONCAT('["', REPLACE(REPLACE(REPLACE(m.files, '\\', '\\\\'), '"', '\\"'), ',', '","'), '"]'),
+        '$[*]' COLUMNS (url VARCHAR(512) PATH '$')
+    ) f
+    WHERE m.files <> '' AND f.url <> ''
+) t
+WHERE CHAR_LENGTH(t.storage_key) <= 255;
+
+ALTER TABLE messages DROP COLUMN files;
@@ -0,0 +1,15 @@
+-- 图片缩略图表，按原图的存储key关联，相同内容的图片共用缩略图
+CREATE TABLE IF NOT EXISTS thumbnails (
+    id bigint PRIMARY KEY AUTO_INCREMENT not null comment '主键ID',
+    source_key VARCHAR(255) NOT NULL comment '原图存储key',
+    max_size int NOT NULL comment '缩略图最长边',
+    storage_key VARCHAR(255) NOT NULL comment '缩略图存储key',
+    size bigint NOT NULL comment '文件大小（字节）',
+    mime_type VARCHAR(100) NOT NULL comment '文件类型',
+    width int NOT NULL comment '宽度',
+    height int NOT NULL comment '高度',
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP() comment '创建时间'
+) comment '图片缩略图表';
+
+create unique index uk_thumbnails_source_key_max_size on thumbnails (source_key, max_size);
+create index idx_thumbnails_storage_key on thumbnails (storage_key);
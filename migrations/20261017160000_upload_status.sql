@@ -0,0 +1,3 @@
+-- 客户端通过预签名地址直传的文件，确认之前是 pending 状态，不能被消息引用
+ALTER TABLE uploads
+    ADD COLUMN status VARCHAR(20) NOT NULL DEFAULT 'completed' comment '状态：pending/completed' AFTER height;
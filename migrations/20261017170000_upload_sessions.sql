@@ -0,0 +1,25 @@
+-- 分片上传会话，过期未完成的会话由定时任务清理
+CREATE TABLE IF NOT EXISTS upload_sessions (
+    id VARCHAR(36) PRIMARY KEY not null comment '会话ID',
+    ws_id bigint NOT NULL comment '空间ID',
+    chat_id bigint NOT NULL comment '聊天ID',
+    uploader_id bigint NOT NULL comment '上传者ID',
+    filename VARCHAR(255) NOT NULL comment '原始文件名',
+    mime_type VARCHAR(100) NOT NULL comment '文件类型',
+    size bigint NOT NULL comment '文件大小（字节）',
+    chunk_size bigint NOT NULL comment '分片大小（字节）',
+    chunk_count int NOT NULL comment '分片数量',
+    expires_at DATETIME NOT NULL comment '过期时间',
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP() comment '创建时间'
+) comment '分片上传会话表';
+
+create index idx_upload_sessions_expires_at on upload_sessions (expires_at);
+
+-- 已经收到的分片
+CREATE TABLE IF NOT EXISTS upload_session_chunks (
+    session_id VARCHAR(36) NOT NULL comment '会话ID',
+    chunk_index int NOT NULL comment '分片序号，从0开始',
+    size bigint NOT NULL comment '分片大小（字节）',
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP() comment '创建时间',
+    PRIMARY KEY (session_id, chunk_index)
+) comment '分片上传已收到的分片表';
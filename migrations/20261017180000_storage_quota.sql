@@ -0,0 +1,16 @@
+-- 空间的存储用量、配额和附件保留天数，配额和保留天数为空时使用配置文件中的默认值
+ALTER TABLE workspaces
+    ADD COLUMN storage_used bigint NOT NULL DEFAULT 0 comment '已使用的存储（字节）' AFTER owner_id,
+    ADD COLUMN storage_quota bigint NULL comment '存储配额（字节），0表示不限制' AFTER storage_used,
+    ADD COLUMN retention_days int NULL comment '附件保留天数，0表示永久保留' AFTER storage_quota;
+
+UPDATE workspaces w
+SET w.storage_used = (
+    SELECT COALESCE(SUM(u.size), 0) FROM uploads u WHERE u.ws_id = w.id AND u.status = 'completed'
+);
+
+-- 附件过期被清理后，消息标记为附件已过期
+ALTER TABLE messages
+    ADD COLUMN attachments_expired BOOLEAN NOT NULL DEFAULT FALSE comment '附件是否已过期清理' AFTER content;
+
+create index idx_uploads_ws_id_created_at on uploads (ws_id, created_at);
@@ -0,0 +1,16 @@
+-- 消息编辑和删除，删除的消息保留记录（墓碑），内容清空
+ALTER TABLE messages
+    ADD COLUMN edited_at DATETIME NULL comment '最后编辑时间' AFTER attachments_expired,
+    ADD COLUMN deleted_at DATETIME NULL comment '删除时间' AFTER edited_at,
+    ADD COLUMN deleted_by bigint NULL comment '删除者ID' AFTER deleted_at;
+
+-- 消息的编辑历史，保存每次编辑前的内容
+CREATE TABLE IF NOT EXISTS message_edits (
+    id bigint PRIMARY KEY AUTO_INCREMENT not null comment '主键ID',
+    message_id bigint NOT NULL comment '消息ID',
+    editor_id bigint NOT NULL comment '编辑者ID',
+    content TEXT NOT NULL comment '编辑前的内容',
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP() comment '编辑时间'
+);
+
+create index idx_message_edits_message_id on message_edits (message_id);
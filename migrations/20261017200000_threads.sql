@@ -0,0 +1,7 @@
+-- 话题回复，回复消息记录所属的父消息，父消息记录回复数和最后回复时间
+ALTER TABLE messages
+    ADD COLUMN parent_message_id bigint NULL comment '父消息ID，话题回复时不为空' AFTER sender_id,
+    ADD COLUMN reply_count int NOT NULL DEFAULT 0 comment '回复数' AFTER parent_message_id,
+    ADD COLUMN last_reply_at DATETIME NULL comment '最后回复时间' AFTER reply_count;
+
+create index idx_messages_parent_message_id on messages (parent_message_id, id);
@@ -0,0 +1,8 @@
+-- 消息表情回应，每个用户对同一条消息的同一个表情只能回应一次
+CREATE TABLE IF NOT EXISTS message_reactions (
+    message_id bigint NOT NULL comment '消息ID',
+    user_id bigint NOT NULL comment '用户ID',
+    emoji VARCHAR(32) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL comment '表情',
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP() comment '创建时间',
+    PRIMARY KEY (message_id, user_id, emoji)
+) comment '消息表情回应表';
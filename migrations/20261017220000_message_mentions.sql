@@ -0,0 +1,12 @@
+-- 消息中 @ 到的用户，@all 时记录聊天室中除发送者以外的所有成员
+CREATE TABLE IF NOT EXISTS message_mentions (
+    id bigint PRIMARY KEY AUTO_INCREMENT not null comment '主键ID',
+    message_id bigint NOT NULL comment '消息ID',
+    chat_id bigint NOT NULL comment '聊天ID',
+    user_id bigint NOT NULL comment '被提及的用户ID',
+    read_at DATETIME NULL comment '已读时间，为空表示未读',
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP() comment '创建时间'
+) comment '消息提及表';
+
+create unique index idx_message_mentions_message_id_user_id on message_mentions (message_id, user_id);
+create index idx_message_mentions_user_id_read_at on message_mentions (user_id, read_at);
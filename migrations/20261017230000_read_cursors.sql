@@ -0,0 +1,9 @@
+-- 成员的已读位置，未读数是该位置之后其他成员发送的消息数
+ALTER TABLE chat_members
+    ADD COLUMN last_read_message_id bigint NOT NULL DEFAULT 0 comment '最后已读的消息ID' AFTER role;
+
+-- 已有成员视为已经读完所有消息
+UPDATE chat_members cm
+SET cm.last_read_message_id = (
+    SELECT COALESCE(MAX(m.id), 0) FROM messages m WHERE m.chat_id = cm.chat_id
+);
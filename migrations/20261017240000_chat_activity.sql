@@ -0,0 +1,15 @@
+-- 聊天室的最后一条消息和最后活跃时间，聊天列表按最后活跃时间排序
+ALTER TABLE chats
+    ADD COLUMN last_message_id bigint NULL comment '最后一条消息ID' AFTER type,
+    ADD COLUMN last_activity_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP() comment '最后活跃时间' AFTER last_message_id;
+
+UPDATE chats c
+SET c.last_message_id = (
+        SELECT MAX(m.id) FROM messages m WHERE m.chat_id = c.id AND m.parent_message_id IS NULL
+    ),
+    c.last_activity_at = COALESCE(
+        (SELECT MAX(m.created_at) FROM messages m WHERE m.chat_id = c.id AND m.parent_message_id IS NULL),
+        c.created_at
+    );
+
+create index idx_chats_last_activity_at_id on chats (last_activity_at, id);
@@ -0,0 +1,5 @@
+-- 客户端生成的消息ID，客户端重试发送时用来去重
+ALTER TABLE messages
+    ADD COLUMN client_msg_id VARCHAR(64) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NULL comment '客户端消息ID，区分大小写' AFTER sender_id;
+
+create unique index idx_messages_chat_id_sender_id_client_msg_id on messages (chat_id, sender_id, client_msg_id);
@@ -0,0 +1,13 @@
+-- 消息类型和结构化内容（JSON），卡片消息的内容保存在 payload 中
+ALTER TABLE messages
+    ADD COLUMN kind VARCHAR(20) NOT NULL DEFAULT 'text' comment '消息类型 text markdown image file system card' AFTER parent_message_id,
+    ADD COLUMN payload TEXT NULL comment '结构化内容' AFTER content;
+
+-- 已有的只有附件的消息按附件类型区分图片和文件
+UPDATE messages m
+SET m.kind = IF(
+    NOT EXISTS (SELECT 1 FROM attachments a WHERE a.message_id = m.id AND a.mime_type NOT LIKE 'image/%'),
+    'image',
+    'file'
+)
+WHERE m.content = '' AND EXISTS (SELECT 1 FROM attachments a WHERE a.message_id = m.id);
@@ -0,0 +1,2 @@
+-- 消息全文索引，使用 ngram 分词以支持中文搜索
+ALTER TABLE messages ADD FULLTEXT INDEX ft_messages_content (content) WITH PARSER ngram;
@@ -0,0 +1,10 @@
+-- 聊天室置顶消息，同一条消息在聊天室中只能置顶一次
+CREATE TABLE IF NOT EXISTS message_pins (
+    chat_id bigint NOT NULL comment '聊天ID',
+    message_id bigint NOT NULL comment '消息ID',
+    pinned_by bigint NOT NULL comment '置顶操作人ID',
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP() comment '置顶时间',
+    PRIMARY KEY (chat_id, message_id)
+) comment '置顶消息表';
+
+create index idx_message_pins_message_id on message_pins (message_id);
@@ -0,0 +1,5 @@
+-- 直传未确认的上传记录的过期时间，过期后由定时任务删除记录和文件
+ALTER TABLE uploads
+    ADD COLUMN expires_at DATETIME NULL comment '直传未确认的过期时间' AFTER status;
+
+create index idx_uploads_status_expires_at on uploads (status, expires_at);
@@ -0,0 +1,2 @@
+-- 聊天记录按消息ID分页，只查询聊天室的消息（不含话题回复）
+create index idx_messages_chat_id_parent_message_id_id on messages (chat_id, parent_message_id, id);
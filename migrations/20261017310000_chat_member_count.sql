@@ -0,0 +1,6 @@
+-- 聊天室的成员数，随成员加入和离开更新，聊天列表不再逐个统计
+ALTER TABLE chats
+    ADD COLUMN member_count bigint NOT NULL DEFAULT 0 comment '成员数' AFTER type;
+
+UPDATE chats c
+SET c.member_count = (SELECT COUNT(*) FROM chat_members cm WHERE cm.chat_id = c.id);
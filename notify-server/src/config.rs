@@ -1,6 +1,7 @@
 use std::{fs::File, ops::Deref};
 
 use anyhow::bail;
+use chat_core::utils::jwt::DecodingKey;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -17,7 +18,24 @@ pub struct ServerConfig {
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AuthConfig {
-    pub public_key: String,
+    // 所有仍然有效的公钥，轮换密钥时新旧公钥同时配置
+    pub public_keys: Vec<PublicKeyConfig>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PublicKeyConfig {
+    pub kid: String,
+    pub key: String,
+}
+
+impl AuthConfig {
+    pub fn decoding_key(&self) -> Result<DecodingKey, jwt_simple::Error> {
+        DecodingKey::load_set(
+            self.public_keys
+                .iter()
+                .map(|k| (k.kid.as_str(), k.key.as_str())),
+        )
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
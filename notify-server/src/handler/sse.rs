@@ -35,6 +35,11 @@ pub(crate) async fn sse_handler(
             ChatEvent::UserJoin(_) => "UserJoin",
             ChatEvent::UserLeave(_) => "UserLeave",
             ChatEvent::MessageSend(_) => "MessageSend",
+            ChatEvent::MessageEdit(_) => "MessageEdit",
+            ChatEvent::MessageDelete(_) => "MessageDelete",
+            ChatEvent::ReactionChanged(_) => "ReactionChanged",
+            ChatEvent::ReadReceipt(_) => "ReadReceipt",
+            ChatEvent::MessagePinned(_) => "MessagePinned",
         };
         let v = serde_json::to_string(&v).expect("Failed to serialize event");
         debug!("Sending event {}: {:?}", name, v);
@@ -1,5 +1,12 @@
 use anyhow::Result;
-use chat_core::{TokenVerify, event::ChatEvent, models::user::CurUser, utils::jwt::DecodingKey};
+use chat_core::{
+    TokenVerify,
+    event::ChatEvent,
+    utils::{
+        jwt::{DecodingKey, VerifiedToken},
+        revoked_token::RevokedTokens,
+    },
+};
 pub use config::*;
 use dashmap::DashMap;
 pub use router::*;
@@ -25,6 +32,8 @@ pub struct AppStateInner {
     pub app_config: AppConfig,
     pub users: UserMap,
     pub redis_client: Arc<redis::Client>,
+    pub revoked_tokens: RevokedTokens,
+    pub decoding_key: DecodingKey,
 }
 
 impl AppState {
@@ -46,16 +55,25 @@ impl Deref for AppState {
 impl AppStateInner {
     pub fn try_new(app_config: AppConfig) -> Result<Self> {
         let redis_client = redis::Client::open(app_config.redis.url.clone())?;
+        let revoked_tokens = RevokedTokens::new(redis_client.clone());
+        // 公钥只在启动时解析一次
+        let decoding_key = app_config.auth.decoding_key()?;
         Ok(Self {
             app_config,
             users: Arc::new(DashMap::new()),
             redis_client: Arc::new(redis_client),
+            revoked_tokens,
+            decoding_key,
         })
     }
 }
 
 impl TokenVerify for AppState {
-    fn verify_token(&self, token: &str) -> std::result::Result<CurUser, jwt_simple::Error> {
-        DecodingKey::load(&self.inner.app_config.auth.public_key)?.verify(token)
+    fn verify_token(&self, token: &str) -> std::result::Result<VerifiedToken, jwt_simple::Error> {
+        self.decoding_key.decode(token)
+    }
+
+    async fn is_token_revoked(&self, jti: &str) -> Result<bool> {
+        self.revoked_tokens.is_revoked(jti).await
     }
 }
@@ -4,7 +4,7 @@ use crate::AppState;
 use anyhow::Result;
 use chat_core::event::ChatEvent;
 use tokio_stream::StreamExt;
-use tracing::info;
+use tracing::{info, warn};
 
 pub async fn start_background_task(state: AppState) -> Result<()> {
     tokio::spawn(async move {
@@ -23,14 +23,26 @@ pub async fn start_background_task(state: AppState) -> Result<()> {
         let mut pubsub_stream = pubsub_conn.on_message();
 
         while let Some(msg) = pubsub_stream.next().await {
+            // 无法解析的消息只记录日志，不能影响后续消息的推送
+            let payload = match msg.get_payload::<String>() {
+                Ok(payload) => payload,
+                Err(e) => {
+                    warn!("invalid message payload: {}", e);
+                    continue;
+                }
+            };
             info!(
                 "receive message: {}, payload: {}",
                 msg.get_channel_name(),
-                msg.get_payload::<String>().unwrap()
+                payload
             );
-
-            let payload = msg.get_payload::<String>().unwrap();
-            let event: ChatEvent = serde_json::from_str(&payload).unwrap();
+            let event: ChatEvent = match serde_json::from_str(&payload) {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("invalid chat event: {}, payload: {}", e, payload);
+                    continue;
+                }
+            };
             process_event(event, &state).await;
         }
     });
@@ -41,51 +53,12 @@ pub async fn start_background_task(state: AppState) -> Result<()> {
 async fn process_event(event: ChatEvent, state: &AppState) {
     let users = state.users.clone();
     let event = Arc::new(event);
-    match event.as_ref() {
-        ChatEvent::ChatCreate(msg) => {
-            for user_id in msg.members.iter() {
-                if let Some(user) = users.get(user_id)
-                    && let Err(e) = user.value().send(Arc::clone(&event))
-                {
-                    info!("send message to user failed: {}", e);
-                }
-            }
-        }
-        ChatEvent::ChatDrop(msg) => {
-            for user_id in msg.members.iter() {
-                if let Some(user) = users.get(user_id)
-                    && let Err(e) = user.value().send(Arc::clone(&event))
-                {
-                    info!("send message to user failed: {}", e);
-                }
-            }
-        }
-        ChatEvent::MessageSend(msg) => {
-            for user_id in msg.members.iter() {
-                if let Some(user) = users.get(user_id)
-                    && let Err(e) = user.value().send(Arc::clone(&event))
-                {
-                    info!("send message to user failed: {}", e);
-                }
-            }
-        }
-        ChatEvent::UserJoin(msg) => {
-            for user_id in msg.members.iter() {
-                if let Some(user) = users.get(user_id)
-                    && let Err(e) = user.value().send(Arc::clone(&event))
-                {
-                    info!("send message to user failed: {}", e);
-                }
-            }
-        }
-        ChatEvent::UserLeave(msg) => {
-            for user_id in msg.members.iter() {
-                if let Some(user) = users.get(user_id)
-                    && let Err(e) = user.value().send(Arc::clone(&event))
-                {
-                    info!("send message to user failed: {}", e);
-                }
-            }
+    // 所有事件都推送给事件中的聊天室成员
+    for user_id in event.members() {
+        if let Some(user) = users.get(user_id)
+            && let Err(e) = user.value().send(Arc::clone(&event))
+        {
+            info!("send message to user failed: {}", e);
         }
     }
 }